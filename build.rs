@@ -0,0 +1,7 @@
+// Bakes build info into the binary for `piper --version`.
+fn main() {
+    let target = std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_string());
+    let profile = std::env::var("PROFILE").unwrap_or_else(|_| "unknown".to_string());
+    println!("cargo:rustc-env=PIPER_TARGET={}", target);
+    println!("cargo:rustc-env=PIPER_PROFILE={}", profile);
+}
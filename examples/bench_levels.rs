@@ -0,0 +1,79 @@
+//! Compares zstd levels on a sample file or directory.
+//!
+//! Usage: cargo run --release --example bench_levels -- <path> [levels...]
+//!
+//! The sample is copied for every level, so the original is never touched.
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use piper::compressor;
+
+const DEFAULT_LEVELS: [i32; 5] = [1, 3, 9, 15, 19];
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let sample = PathBuf::from(args.next().context("Usage: bench_levels <path> [levels...]")?);
+    let levels: Vec<i32> = {
+        let parsed: Vec<i32> = args.filter_map(|a| a.parse().ok()).collect();
+        if parsed.is_empty() { DEFAULT_LEVELS.to_vec() } else { parsed }
+    };
+
+    let scratch = std::env::temp_dir().join(format!("piper_bench_{}", std::process::id()));
+    std::fs::create_dir_all(&scratch)?;
+
+    println!("Sample: {}", sample.display());
+    println!("{:>5}  {:>12}  {:>12}  {:>8}  {:>10}", "Level", "Original", "Compressed", "Ratio", "Time");
+
+    for level in levels {
+        // compress_file replaces its input, so work on a fresh copy each time
+        let copy = scratch.join(sample.file_name().context("Sample has no file name")?);
+        copy_tree(&sample, &copy)?;
+
+        let start = Instant::now();
+        let stats = compressor::compress_file(&copy, level)?;
+        let elapsed = start.elapsed();
+
+        let ratio = stats.original_size as f64 / stats.compressed_size.max(1) as f64;
+        println!(
+            "{:>5}  {:>12}  {:>12}  {:>7.2}x  {:>9.2}s",
+            level, stats.original_size, stats.compressed_size, ratio, elapsed.as_secs_f64()
+        );
+
+        remove_any(&stats.output_path)?;
+        if copy.exists() {
+            remove_any(&copy)?;
+        }
+    }
+
+    std::fs::remove_dir_all(&scratch)?;
+    Ok(())
+}
+
+fn copy_tree(src: &Path, dest: &Path) -> Result<()> {
+    if src.is_file() {
+        std::fs::copy(src, dest)?;
+        return Ok(());
+    }
+
+    for entry in walkdir::WalkDir::new(src) {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(src)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn remove_any(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
@@ -1,6 +1,5 @@
 use std::path::PathBuf;
 use std::fs::File;
-use std::io::Write;
 
 // Mock the compressor module content here for isolation
 fn compress_file(input_path: &std::path::Path, level: i32) -> anyhow::Result<()> {
@@ -17,7 +16,6 @@ fn compress_file(input_path: &std::path::Path, level: i32) -> anyhow::Result<()>
 }
 
 fn compress_directory(input_path: &std::path::Path, level: i32) -> anyhow::Result<()> {
-    let original_size = 1000; // Fake it
     println!("DEBUG: compress_directory called on {:?}", input_path);
 
     let output_path = PathBuf::from(format!("{}.tar.zst", input_path.to_string_lossy()));
@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+
+use crate::backup;
+use crate::persist::{self, sibling};
+
+/// One compression run, as shown on the Analytics tab.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: i64, // Unix seconds, when the run finished
+    pub bytes_saved: u64,
+    pub items: usize,
+    /// Level picked for the run (per-type tuning may have varied it per file).
+    /// 0 in entries from before it was recorded.
+    #[serde(default)]
+    pub level: i32,
+    /// e.g. "zstd"; empty in entries from before it was recorded.
+    #[serde(default)]
+    pub backend: String,
+    /// A dry run: `bytes_saved` is the projection, and nothing was written.
+    /// Kept out of the totals so projections can be compared with actuals.
+    #[serde(default)]
+    pub simulated: bool,
+}
+
+/// How many artifacts the leaderboard keeps per ranking.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// One compressed item, for the leaderboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub path: PathBuf, // The original's path
+    pub original_size: u64,
+    pub compressed_size: u64,
+}
+
+impl Artifact {
+    pub fn saved(&self) -> u64 {
+        self.original_size.saturating_sub(self.compressed_size)
+    }
+
+    /// Original over compressed size, e.g. 8.0 for an eighth of the size.
+    pub fn ratio(&self) -> f64 {
+        self.original_size as f64 / self.compressed_size as f64
+    }
+}
+
+/// Every recorded run, oldest first, persisted as JSON.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AnalyticsHistory {
+    pub entries: Vec<HistoryEntry>,
+    /// Best artifacts ever: the top `LEADERBOARD_SIZE` by ratio plus the
+    /// top by bytes saved. Missing from files written before it existed.
+    #[serde(default)]
+    pub leaderboard: Vec<Artifact>,
+    /// File it was loaded from and saves to; `None` when there is nowhere to keep it.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// Runs and artifacts recorded here but not yet saved. Saving merges
+    /// them into whatever is on disk by then, which another Piper may have added to.
+    #[serde(skip)]
+    unsaved_entries: Vec<HistoryEntry>,
+    #[serde(skip)]
+    unsaved_artifacts: Vec<Artifact>,
+}
+
+impl AnalyticsHistory {
+    /// `history.json` in the data directory (see `backup::data_dir`).
+    pub fn default_path() -> Option<PathBuf> {
+        backup::data_dir().map(|dir| dir.join("history.json"))
+    }
+
+    /// Where this history is kept.
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The history at the default location; see `load_from`.
+    pub fn load() -> Self {
+        Self::load_from(Self::default_path())
+    }
+
+    /// The history saved at `path`, or an empty one there if there is none
+    /// (or it's unreadable). Later saves go to the same file.
+    pub fn load_from(path: Option<PathBuf>) -> Self {
+        let history = path.as_deref().and_then(Self::read).unwrap_or_default();
+        AnalyticsHistory { path, ..history }
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// Writes the unsaved runs and artifacts on top of what is on disk now,
+    /// so concurrent instances add to the file instead of overwriting each
+    /// other. A lock file keeps their saves from interleaving.
+    pub fn save(&mut self) -> Result<()> {
+        let path = self.path.clone()
+            .context("No home directory to keep history in; set history_path or PIPER_DATA_DIR")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock = File::create(sibling(&path, "lock"))?;
+        lock.lock().with_context(|| format!("Failed to lock {}", path.display()))?;
+
+        // Unreadable (or gone): nothing to merge, keep what is in memory
+        if let Some(on_disk) = Self::read(&path) {
+            self.entries = on_disk.entries;
+            self.entries.extend(self.unsaved_entries.iter().cloned());
+            self.leaderboard = on_disk.leaderboard;
+            self.rank(self.unsaved_artifacts.clone());
+        }
+
+        persist::write_json(&path, self)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.unsaved_entries.clear();
+        self.unsaved_artifacts.clear();
+        Ok(())
+    }
+
+    /// Records a finished run and saves.
+    pub fn add_entry(&mut self, bytes_saved: u64, items: usize, level: i32, backend: &str) -> Result<()> {
+        self.push(bytes_saved, items, level, backend, false)
+    }
+
+    /// Records a dry run's projected savings and saves.
+    pub fn add_simulated(&mut self, projected: u64, items: usize, level: i32, backend: &str) -> Result<()> {
+        self.push(projected, items, level, backend, true)
+    }
+
+    fn push(&mut self, bytes_saved: u64, items: usize, level: i32, backend: &str, simulated: bool) -> Result<()> {
+        let entry = HistoryEntry {
+            timestamp: chrono::Local::now().timestamp(),
+            bytes_saved,
+            items,
+            level,
+            backend: backend.to_string(),
+            simulated,
+        };
+        self.entries.push(entry.clone());
+        self.unsaved_entries.push(entry);
+        self.save()
+    }
+
+    /// Adds a run's artifacts to the leaderboard, dropping any that rank in
+    /// neither top list. Not saved until the run itself is recorded.
+    pub fn add_artifacts(&mut self, artifacts: impl IntoIterator<Item = Artifact>) {
+        let artifacts: Vec<Artifact> = artifacts.into_iter().collect();
+        self.unsaved_artifacts.extend(artifacts.iter().cloned());
+        self.rank(artifacts);
+    }
+
+    fn rank(&mut self, artifacts: Vec<Artifact>) {
+        for artifact in artifacts {
+            // Compressed again after a restore: the newest result counts
+            self.leaderboard.retain(|a| a.path != artifact.path);
+            self.leaderboard.push(artifact);
+        }
+        let keep: Vec<Artifact> = self.top_by_ratio(LEADERBOARD_SIZE).into_iter()
+            .chain(self.top_by_saved(LEADERBOARD_SIZE))
+            .cloned()
+            .collect();
+        self.leaderboard.retain(|a| keep.contains(a));
+    }
+
+    /// Leaderboard entries by compression ratio, best first.
+    pub fn top_by_ratio(&self, n: usize) -> Vec<&Artifact> {
+        let mut top: Vec<_> = self.leaderboard.iter().collect();
+        top.sort_by(|a, b| b.ratio().total_cmp(&a.ratio()));
+        top.truncate(n);
+        top
+    }
+
+    /// Leaderboard entries by bytes saved, most first.
+    pub fn top_by_saved(&self, n: usize) -> Vec<&Artifact> {
+        let mut top: Vec<_> = self.leaderboard.iter().collect();
+        top.sort_by_key(|a| std::cmp::Reverse(a.saved()));
+        top.truncate(n);
+        top
+    }
+
+    /// Real runs, oldest first.
+    pub fn runs(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter().filter(|e| !e.simulated)
+    }
+
+    /// Dry runs, oldest first.
+    pub fn projections(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter().filter(|e| e.simulated)
+    }
+
+    /// Bytes saved by real runs; projections don't count.
+    pub fn total_saved(&self) -> u64 {
+        self.runs().map(|e| e.bytes_saved).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_without_level_still_load() -> Result<()> {
+        let history: AnalyticsHistory = serde_json::from_str(
+            r#"{"entries": [{"timestamp": 1700000000, "bytes_saved": 2048, "items": 3}]}"#,
+        )?;
+
+        assert_eq!(history.entries[0].level, 0);
+        assert_eq!(history.entries[0].backend, "");
+        assert!(!history.entries[0].simulated);
+        assert_eq!(history.total_saved(), 2048);
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_saves_where_it_was_loaded_from() -> Result<()> {
+        let dir = PathBuf::from("test_history_location");
+        let path = dir.join("nested").join("runs.json");
+
+        let mut history = AnalyticsHistory::load_from(Some(path.clone()));
+        assert!(history.entries.is_empty());
+        history.add_entry(4096, 2, 9, "zstd")?;
+        history.add_simulated(1 << 30, 40, 19, "zstd")?;
+
+        let reloaded = AnalyticsHistory::load_from(Some(path.clone()));
+        fs::remove_dir_all(&dir)?;
+        assert_eq!(reloaded.get_path(), Some(path.as_path()));
+        assert_eq!(reloaded.total_saved(), 4096, "Projections stay out of the total");
+        assert_eq!(reloaded.projections().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_instances_merge_their_runs() -> Result<()> {
+        let dir = PathBuf::from("test_history_merge");
+        let path = dir.join("history.json");
+        let artifact = |name: &str| Artifact { path: PathBuf::from(name), original_size: 1000, compressed_size: 100 };
+
+        // Both start from the same (empty) file, then each records a run
+        let mut first = AnalyticsHistory::load_from(Some(path.clone()));
+        let mut second = AnalyticsHistory::load_from(Some(path.clone()));
+        first.add_artifacts([artifact("first.log")]);
+        first.add_entry(100, 1, 3, "zstd")?;
+        second.add_artifacts([artifact("second.log")]);
+        second.add_entry(200, 1, 3, "zstd")?;
+        first.add_entry(400, 1, 3, "zstd")?;
+
+        let merged = AnalyticsHistory::load_from(Some(path.clone()));
+        let leftovers = fs::read_dir(&dir)?.count();
+        fs::remove_dir_all(&dir)?;
+        assert_eq!(merged.total_saved(), 700, "No instance overwrote another's runs");
+        assert_eq!(first.total_saved(), 700, "Saving picks up the other instance's runs");
+        assert_eq!(merged.leaderboard.len(), 2);
+        assert_eq!(leftovers, 2, "Only the history and its lock file remain");
+        Ok(())
+    }
+
+    #[test]
+    fn test_leaderboard_keeps_the_best_of_each_ranking() {
+        let artifact = |name: &str, original_size, compressed_size| Artifact {
+            path: PathBuf::from(name), original_size, compressed_size,
+        };
+        let mut history = AnalyticsHistory::default();
+        // Small but squeezed hard, then big with a modest ratio
+        history.add_artifacts((0..LEADERBOARD_SIZE as u64).map(|i| artifact(&format!("log{}", i), 1000, 10 + i)));
+        history.add_artifacts((0..LEADERBOARD_SIZE as u64).map(|i| artifact(&format!("dump{}", i), 1 << 30, (1 << 29) + i)));
+        history.add_artifacts([artifact("meh", 1000, 900)]);
+
+        assert_eq!(history.leaderboard.len(), 2 * LEADERBOARD_SIZE, "Neither list wants `meh`");
+        assert_eq!(history.top_by_ratio(1)[0].path, PathBuf::from("log0"));
+        assert_eq!(history.top_by_saved(1)[0].path, PathBuf::from("dump0"));
+
+        history.add_artifacts([artifact("log0", 1000, 400)]);
+        let log0: Vec<_> = history.leaderboard.iter().filter(|a| a.path == Path::new("log0")).collect();
+        assert_eq!(log0.len(), 1, "Recompressing replaces the old result");
+        assert_eq!(log0[0].compressed_size, 400);
+    }
+}
@@ -1,43 +1,163 @@
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::thread;
-use std::time::Duration;
-use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "tui")]
 use ratatui::widgets::TableState;
-use crossterm::event::KeyCode;
+#[cfg(feature = "tui")]
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use rayon::prelude::*;
 
-use crate::spyder::{self, Spyder};
-use crate::compressor::{self, CompressionStats};
+use crate::spyder::{self, Category, ScannedItem, Spyder, TimeBasis};
+use crate::compressor::{self, Backend, CompressOptions, CompressionStats, ProgressUpdate};
+#[cfg(feature = "tui")]
+use crate::compressor::RestoreOptions;
+use crate::estimate::SavingsEstimator;
+use crate::analytics::{AnalyticsHistory, Artifact};
+use crate::recent::RecentScans;
+#[cfg(feature = "tui")]
+use crate::backup;
+use crate::memory;
+use crate::naming::Naming;
+#[cfg(feature = "tui")]
+use crate::sidecar::Sidecar;
+use crate::config::{Column, ListView, Profile, TrashFallback};
+use crate::units::format_size;
+use crate::worker::Worker;
 
 pub struct FileItem {
-    pub path: String,
+    pub path: PathBuf, // As found on disk; only converted (lossily) for display
     pub original_size: u64,
     pub compressed_size: Option<u64>,
     pub status: FileStatus,
-    pub reason: String,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub category: Category,
+    pub reason: String, // Starts as the category's reason; replaced by skip/error details
+    pub age_days: Option<u64>,
+    pub file_count: Option<u64>, // Directories only
+    pub project: Option<PathBuf>, // Nearest project root under the scan path, if any
     pub selected: bool,
 }
 
+impl FileItem {
+    /// Nothing left to do with it: compressed or deleted.
+    pub fn is_completed(&self) -> bool {
+        matches!(self.status, FileStatus::Done | FileStatus::Deleted)
+    }
+
+    /// Like `From`, also recording which project under `scan_root` it belongs to.
+    fn in_project(res: ScannedItem, scan_root: &Path) -> Self {
+        let project = spyder::project_root(&res.path, scan_root);
+        FileItem { project, ..FileItem::from(res) }
+    }
+}
+
+impl From<ScannedItem> for FileItem {
+    fn from(res: ScannedItem) -> Self {
+        FileItem {
+            reason: res.reason(),
+            status: if res.skip_reason.is_some() { FileStatus::Skipped } else { FileStatus::Found },
+            path: res.path,
+            original_size: res.size,
+            compressed_size: None,
+            category: res.category,
+            age_days: res.age_days,
+            file_count: res.file_count,
+            project: None,
+            selected: false,
+        }
+    }
+}
+
+/// Granularity of byte-level compression progress updates.
+const PROGRESS_STEP: u64 = 4 * 1024 * 1024;
+/// Same for the file count of directories being archived.
+const FILES_STEP: u64 = 256;
+
+/// How often the UI wakes up to redraw and drain worker messages when idle.
+pub const DEFAULT_TICK_RATE_MS: u64 = 250;
+/// Tick rates outside this range are clamped: faster burns CPU, slower feels frozen.
+pub const TICK_RATE_RANGE_MS: (u64, u64) = (20, 1000);
+/// One spinner frame, kept on wall-clock time so the tick rate doesn't change its speed.
+const SPINNER_FRAME_MS: u128 = 120;
+
+/// How long a `status_message` stays in the footer.
+pub const STATUS_MESSAGE_TTL: Duration = Duration::from_secs(4);
+
+/// How many of the largest candidates `t` selects.
+#[cfg(feature = "tui")]
+pub const DEFAULT_TOP_N: usize = 10;
+
+/// Well under the common 256/1024 soft limits, leaving room for the TUI itself.
+pub const DEFAULT_MAX_OPEN_FILES: usize = 128;
+
 #[derive(PartialEq)]
 pub enum FileStatus {
     Found,
     Compressing,
     Done,
     Error,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
     Deleted,
     Restored,
+    Skipped, // Deliberately left alone; `reason` says why
 }
 
 pub enum AppMessage {
-    ScanComplete(Vec<FileItem>),
+    ScanComplete(Vec<FileItem>, Vec<String>, u64), // items, skipped-path summary, entries visited
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    ExpandComplete(usize, Vec<FileItem>), // index of expanded dir, its large files
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    SizeComplete(usize, Option<spyder::DirStats>), // index, its size now (`None` if it's gone)
+    CompressionBytes(usize, u64), // index, input bytes consumed so far
+    CompressionFiles(usize, u64, u64), // index, files archived so far, files total (directories only)
     CompressionProgress(usize, Result<CompressionStats, String>),
     CompressionDone,
-    RestorationDone(usize, bool), // index, success
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    RestorationDone(usize, Result<(), String>), // index, outcome
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    RestorationFinished,          // whole restore batch is done
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Size, // Biggest first (scan default)
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    Age,  // Oldest first
+}
+
+/// One line of the Scanner table. Without grouping every row is an item.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListRow {
+    /// Section header: project root (`None` for items outside any project),
+    /// item count, aggregate size, whether its items are hidden.
+    Project(Option<PathBuf>, usize, u64, bool),
+    Item(usize),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
 pub enum AppTab {
     Scanner,
+    Analytics,
+    Status,
+    Problems,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning, // Left alone or incomplete, e.g. a skip or unreadable paths
+    Error,   // An action failed
+}
+
+/// Something that went wrong this session, listed on the Problems tab.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub struct Problem {
+    pub severity: Severity,
+    pub path: PathBuf,
+    pub reason: String, // What failed, or why it was skipped
+    pub message: Option<String>, // The underlying error, when there is one
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -50,18 +170,92 @@ pub struct App {
     pub view: AppView, // New field for View State
     pub items: Vec<FileItem>,
     pub list_state: TableState,
+    pub sort_order: SortOrder,
+    pub group_by_project: bool, // Scanner rows sectioned by project root
+    pub hide_completed: bool, // Leave Done and Deleted items out of the Scanner rows
+    pub list_view: ListView, // Which Scanner columns are shown
+    pub columns: Vec<Column>, // The configured set `ListView::Custom` shows; empty when not configured
+    pub collapsed_projects: HashSet<Option<PathBuf>>, // Sections showing only their header
     pub weissman_score: f64,
     pub total_savings: u64,
+    pub history: AnalyticsHistory, // Past runs, for the Analytics tab
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub leaderboard_by_ratio: bool, // Analytics leaderboard ranks by ratio, else by bytes saved
+    pub recent: RecentScans, // Roots offered on the Home screen
+    batch_savings: u64, // Saved so far by the running compression batch
+    batch_level: i32, // Level the running batch was started with
+    compress_started: Option<Instant>, // When the running batch began, for its summary
+    pub projected_savings: u64, // Estimate for items not yet compressed
+    pub estimator: SavingsEstimator,
     pub is_scanning: bool,
+    pub has_scanned: bool, // A scan finished this session, so an empty list means "nothing found"
+    scan_started: Option<Instant>,
+    pub scan_stats: Option<(u64, Duration)>, // Entries walked and time taken by the latest scan
+    pub refresh: Option<Reselect>, // Set while `r` rescans: what to select again afterwards
+    pub resizing: Option<PathBuf>, // Set while `f` re-measures this item
     pub is_compressing: bool,
     pub is_restoring: bool,
     pub show_details: bool,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub show_level_picker: bool, // Confirm/adjust level before compressing
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub visual_anchor: Option<usize>, // Row where `v` started a range selection
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub pending_restore: Option<(Vec<Target>, usize)>, // Awaiting y/n: targets, how many were moved
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub pending_purge: Option<Vec<usize>>, // Awaiting Shift-Y: items to delete permanently
+    pub compression_summary: Option<CompressionSummary>, // Recap popup after a batch, closed by any key
+    pub confirm_broad_scan: bool, // Awaiting y/n before crawling a dangerous root
+    broad_scan_confirmed: bool, // Asked once per session
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub preview: Option<Result<Vec<(String, u64)>, String>>, // Archive contents popup
+    pub backend_estimate: Option<(usize, BackendEstimate)>, // Row index and its sample comparison, for the details popup
     pub spinner_state: u8,
+    pub tick_rate: Duration, // Redraw/refresh cadence; see DEFAULT_TICK_RATE_MS
+    started: Instant, // Spinner clock
+    pub notice: Option<String>, // One-off footer message, cleared on the next key
+    pub status_message: Option<(String, Instant)>, // Confirmation in the footer, cleared by `tick` after STATUS_MESSAGE_TTL
     pub scan_path: PathBuf,
+    pub exclude: Vec<String>,
+    pub exclude_extensions: Vec<String>, // Never listed, e.g. "sqlite", "db"
+    pub skip_dirs: Vec<String>, // Folder names the scan never enters, besides .git
+    pub big_file_threshold: Option<u64>, // Bytes; list any file this large (`large_file_mb`)
+    pub time_basis: TimeBasis, // Timestamp ages and staleness are measured from
+    pub naming: Naming, // Artifact names, for compress, restore and the scanner alike
+    pub scan_error: Option<String>, // Set when the scan root is unusable
+    pub scan_warnings: Vec<String>, // e.g. "12 paths skipped: permission denied"
+    pub problems: Vec<Problem>, // Errors and warnings this session, oldest first
     pub compression_level: i32,
+    pub profile: Option<Profile>, // Named preset from the config, shown in the header
+    pub backend: Backend, // Encoder settings, from the profile
+    pub adaptive_levels: bool, // Per-type tuning of compression_level
+    pub level_overrides: HashMap<String, i32>, // Extension -> fixed level
+    pub keep_original: bool, // Compress to a copy instead of replacing
+    pub keep_archive: bool, // Restore to a copy, leaving the archive in place
+    pub max_expansion: Option<u64>, // Abort restores that decode past this multiple of the archive
+    pub match_archive_mtime: bool, // Restored items get the archive's time, not their own, for incremental builds
+    pub dereference_symlinks: bool, // Archive link targets rather than the links
+    pub verify: bool, // Check each artifact decodes to the original before removing it
+    pub protect_recent: Option<Duration>, // Leave anything modified this recently alone (compress and delete)
+    pub protected_paths: Vec<PathBuf>, // Never compressed; built-ins plus `protected_paths` from the config
+    pub ascii_mode: bool, // Plain ASCII spinner and status markers
+    pub safe_mode: bool, // Never remove originals: implies keep_original, disables delete
+    pub trash_fallback: TrashFallback, // Where `d` sends things if the OS trash fails
+    pub backup_dir: Option<PathBuf>, // This session's backup folder, when backups are on
+    pub output_dir: Option<PathBuf>, // Write artifacts here instead of next to sources
+    pub max_jobs: Option<usize>, // Cap on parallel compress/restore jobs (None = all cores)
+    pub max_open_files: usize, // Cap on descriptors held by compress/restore jobs
+    pub total_memory: Option<u64>, // Physical RAM, when known; see memory::fit
+    pub memory_fraction: f64, // Share of it a compression batch may use
+    pub last_batch: Vec<(PathBuf, PathBuf)>, // (item path, artifact) from the latest compression run
+    pub compress_total: u64, // Input bytes in the running compression batch
+    pub compress_batch: Vec<usize>, // Item indices in that batch, largest (and so first started) first
+    pub compress_bytes: HashMap<usize, u64>, // Bytes consumed so far, per item index
+    pub compress_files: HashMap<usize, (u64, u64)>, // (archived, total) files, per directory being archived
 
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
     pub current_tab: AppTab,
-    pub rx: Option<Receiver<AppMessage>>,
+    pub worker: Worker<AppMessage>, // Runs background jobs; their messages are drained in `tick`
 }
 
 impl App {
@@ -69,38 +263,157 @@ impl App {
         let mut list_state = TableState::default();
         list_state.select(Some(0));
 
+        let scan_error = check_scan_path(&scan_path);
+
         App {
             view: AppView::Home, // Start at Home
             items: Vec::new(),
             list_state,
+            sort_order: SortOrder::Size,
+            group_by_project: false,
+            hide_completed: false,
+            list_view: ListView::default(),
+            columns: Vec::new(),
+            collapsed_projects: HashSet::new(),
             weissman_score: 5.2,
             total_savings: 0,
+            history: AnalyticsHistory::default(),
+            leaderboard_by_ratio: true,
+            recent: RecentScans::default(),
+            batch_savings: 0,
+            batch_level: compression_level,
+            projected_savings: 0,
+            estimator: SavingsEstimator::default(),
             is_scanning: false,
+            has_scanned: false,
+            scan_started: None,
+            compress_started: None,
+            scan_stats: None,
+            refresh: None,
+            resizing: None,
             is_compressing: false,
             is_restoring: false,
             show_details: false,
+            show_level_picker: false,
+            visual_anchor: None,
+            pending_restore: None,
+            pending_purge: None,
+            compression_summary: None,
+            confirm_broad_scan: false,
+            broad_scan_confirmed: false,
+            preview: None,
+            backend_estimate: None,
             spinner_state: 0,
+            tick_rate: Duration::from_millis(DEFAULT_TICK_RATE_MS),
+            started: Instant::now(),
+            notice: None,
+            status_message: None,
             scan_path,
+            exclude: Vec::new(),
+            exclude_extensions: Vec::new(),
+            skip_dirs: spyder::DEFAULT_SKIP_DIRS.iter().map(|d| d.to_string()).collect(),
+            big_file_threshold: None,
+            time_basis: TimeBasis::default(),
+            naming: Naming::default(),
+            scan_error,
+            scan_warnings: Vec::new(),
+            problems: Vec::new(),
             compression_level,
+            profile: None,
+            backend: Backend::Zstd,
+            adaptive_levels: true,
+            level_overrides: HashMap::new(),
+            keep_original: false,
+            keep_archive: false,
+            max_expansion: Some(compressor::DEFAULT_MAX_EXPANSION),
+            match_archive_mtime: false,
+            dereference_symlinks: false,
+            verify: false,
+            protect_recent: None,
+            protected_paths: compressor::default_protected_paths(),
+            ascii_mode: false,
+            safe_mode: false,
+            trash_fallback: TrashFallback::default(),
+            backup_dir: None,
+            output_dir: None,
+            max_jobs: None,
+            max_open_files: DEFAULT_MAX_OPEN_FILES,
+            total_memory: memory::total_memory(),
+            memory_fraction: memory::DEFAULT_MEMORY_FRACTION,
+            last_batch: Vec::new(),
+            compress_total: 0,
+            compress_batch: Vec::new(),
+            compress_bytes: HashMap::new(),
+            compress_files: HashMap::new(),
 
             current_tab: AppTab::Scanner,
-            rx: None,
+            worker: Worker::new(),
         }
     }
 
-    pub fn handle_input(&mut self, key: KeyCode) {
+    #[cfg(feature = "tui")]
+    pub fn handle_input(&mut self, key: KeyEvent) {
+        self.notice = None;
+        let key = normalize_key(key);
+        // No Ctrl/Alt bindings yet; don't let Ctrl-S act like `s`
+        if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return;
+        }
         match self.view {
             AppView::Home => self.handle_home_input(key),
             AppView::Dashboard => self.handle_dashboard_input(key),
         }
+        self.settle_selection();
+    }
+
+    /// Keeps the cursor on an existing row after items change (scan, sort,
+    /// fold), and closes the details popup once it has no item to show.
+    fn settle_selection(&mut self) {
+        let rows = self.rows().len();
+        if let Some(selected) = self.list_state.selected()
+            && selected >= rows
+        {
+            self.list_state.select(Some(rows.saturating_sub(1)));
+        }
+        if self.show_details && self.selected_item().is_none() {
+            self.show_details = false;
+        }
+        if let Some((row, _)) = &self.backend_estimate
+            && (!self.show_details || self.selected_item() != Some(*row))
+        {
+            self.backend_estimate = None;
+        }
     }
 
-    fn handle_home_input(&mut self, key: KeyCode) {
-        match key {
+    #[cfg(feature = "tui")]
+    fn handle_home_input(&mut self, key: KeyEvent) {
+        match key.code {
             KeyCode::Char('1') | KeyCode::Enter => {
                 self.view = AppView::Dashboard;
                 self.current_tab = AppTab::Scanner;
             }
+            KeyCode::Char('2') => {
+                self.view = AppView::Dashboard;
+                self.current_tab = AppTab::Analytics;
+            }
+            KeyCode::Char('3') => {
+                self.view = AppView::Dashboard;
+                self.current_tab = AppTab::Status;
+            }
+            KeyCode::Char('4') => {
+                self.view = AppView::Dashboard;
+                self.current_tab = AppTab::Problems;
+            }
+            // Recent scan roots are listed as a, b, c, ...
+            KeyCode::Char(c @ 'a'..='z') if c != 'q' && !self.is_scanning && !self.is_compressing => {
+                if let Some(scan) = self.recent.scans.get((c as u8 - b'a') as usize) {
+                    self.scan_path = scan.path.clone();
+                    self.broad_scan_confirmed = false;
+                    self.view = AppView::Dashboard;
+                    self.current_tab = AppTab::Scanner;
+                    self.start_scan();
+                }
+            }
             KeyCode::Char('q') => {
                  // handled by main loop? No, main loop calls app.handle_input.
                  // We don't have a Quit state here. Main loop usually breaks on Q.
@@ -109,22 +422,96 @@ impl App {
         }
     }
 
-    fn handle_dashboard_input(&mut self, key: KeyCode) {
-        match key {
+    #[cfg(feature = "tui")]
+    fn handle_dashboard_input(&mut self, key: KeyEvent) {
+        if self.show_level_picker {
+            self.handle_level_picker_input(key);
+            return;
+        }
+
+        if self.pending_restore.is_some() {
+            self.handle_restore_prompt_input(key);
+            return;
+        }
+
+        if self.compression_summary.take().is_some() {
+            return;
+        }
+
+        if let Some(targets) = self.pending_purge.take() {
+            // Only an explicit Shift-Y goes ahead; any other key backs out
+            if key.code == KeyCode::Char('Y') {
+                self.purge(targets);
+            }
+            return;
+        }
+
+        if self.confirm_broad_scan {
+            self.confirm_broad_scan = false;
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Enter) {
+                self.broad_scan_confirmed = true;
+                self.start_scan();
+            }
+            return;
+        }
+
+        if self.visual_anchor.is_some() {
+            self.handle_visual_input(key);
+            return;
+        }
+
+        // The other tabs are read-only
+        if self.current_tab != AppTab::Scanner {
+            match key.code {
+                KeyCode::Char('o') if self.current_tab == AppTab::Analytics => self.open_history_location(),
+                KeyCode::Char('r') if self.current_tab == AppTab::Analytics => self.leaderboard_by_ratio = !self.leaderboard_by_ratio,
+                KeyCode::Tab => self.next_tab(),
+                KeyCode::BackTab => self.previous_tab(),
+                KeyCode::Esc => self.view = AppView::Home,
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
             KeyCode::Down | KeyCode::Char('j') => self.next(),
             KeyCode::Up | KeyCode::Char('k') => self.previous(),
             KeyCode::Char('s') => self.start_scan(),
-            KeyCode::Char('c') => self.start_compression(),
+            KeyCode::Char('r') => self.rescan(),
+            KeyCode::Char('c') => self.open_level_picker(),
+            KeyCode::Char('t') if !self.is_scanning && !self.is_compressing => {
+                self.select_largest(DEFAULT_TOP_N);
+                self.open_level_picker();
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => self.adjust_level(1),
+            KeyCode::Char('-') => self.adjust_level(-1),
             // Safety: Block operations during active work
             KeyCode::Char('d') if !self.is_compressing && !self.is_restoring => self.delete_item(),
+            KeyCode::Char('D') if !self.is_compressing && !self.is_restoring => self.purge_item(),
             KeyCode::Char('e') if !self.is_compressing && !self.is_restoring => self.restore_item(),
+            KeyCode::Char('u') if !self.is_compressing && !self.is_restoring => self.undo_last_compression(),
+            KeyCode::Char('x') => self.expand_item(),
+            KeyCode::Char('f') => self.resize_item(),
+            KeyCode::Char('p') => self.preview_item(),
+            KeyCode::Char('a') => self.toggle_sort(),
+            KeyCode::Char('A') => self.toggle_select_all(),
+            KeyCode::Char('o') => self.open_location(),
+            KeyCode::Char('y') => self.copy_path(),
+            KeyCode::Char('g') => self.toggle_grouping(),
+            KeyCode::Char('h') => self.toggle_hide_completed(),
+            KeyCode::Char('m') => self.cycle_list_view(),
+            KeyCode::Enter if self.selected_project().is_some() => self.toggle_collapsed(),
             KeyCode::Enter => self.toggle_details(),
 
 
             KeyCode::Char(' ') => self.toggle_selection(),
-            // KeyCode::Tab => self.next_tab(), // Disabled for now
+            KeyCode::Char('v') => self.visual_anchor = self.list_state.selected(),
+            KeyCode::Tab => self.next_tab(),
+            KeyCode::BackTab => self.previous_tab(),
             KeyCode::Esc => {
-                if self.show_details {
+                if self.preview.is_some() {
+                    self.preview = None;
+                } else if self.show_details {
                     self.show_details = false;
                 } else {
                      // Go back to Home
@@ -135,26 +522,386 @@ impl App {
         }
     }
 
+    /// Vim-style range selection: j/k extend from the anchor, Space/Enter
+    /// adds the range to the selection, Esc drops it.
+    #[cfg(feature = "tui")]
+    fn handle_visual_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => self.next(),
+            KeyCode::Up | KeyCode::Char('k') => self.previous(),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                if let Some(range) = self.visual_range() {
+                    for row in &self.rows()[range] {
+                        match row {
+                            ListRow::Item(i) => self.items[*i].selected = true,
+                            ListRow::Project(..) => {}
+                        }
+                    }
+                }
+                self.visual_anchor = None;
+            }
+            KeyCode::Esc | KeyCode::Char('v') => self.visual_anchor = None,
+            _ => {}
+        }
+    }
+
+    /// Rows covered by the pending visual selection, in list order.
+    #[cfg(feature = "tui")]
+    pub fn visual_range(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        let anchor = self.visual_anchor?;
+        let cursor = self.list_state.selected()?;
+        let last = self.rows().len().checked_sub(1)?;
+        Some(anchor.min(cursor).min(last)..=anchor.max(cursor).min(last))
+    }
+
+    #[cfg(feature = "tui")]
+    fn handle_level_picker_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Right | KeyCode::Char('l') => self.adjust_level(1),
+            KeyCode::Char('-') | KeyCode::Left | KeyCode::Char('h') => self.adjust_level(-1),
+            KeyCode::Enter | KeyCode::Char('c') => {
+                self.show_level_picker = false;
+                self.start_compression();
+            }
+            KeyCode::Esc => self.show_level_picker = false,
+            _ => {}
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    fn open_level_picker(&mut self) {
+        if self.is_scanning || self.is_compressing { return; }
+        if self.items.iter().any(|i| i.status == FileStatus::Found) {
+            self.show_level_picker = true;
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    pub fn adjust_level(&mut self, delta: i32) {
+        self.compression_level = (self.compression_level + delta).clamp(compressor::MIN_LEVEL, compressor::MAX_LEVEL);
+    }
+
+    /// Reveals the selected item's folder (or the trash, for deleted items).
+    #[cfg(feature = "tui")]
+    fn open_location(&mut self) {
+        let Some(item) = self.selected_item().map(|i| &self.items[i]) else { return; };
+
+        let result = if item.status == FileStatus::Deleted {
+            open_trash()
+        } else {
+            let dir = item.path.parent().unwrap_or(Path::new(".")).to_path_buf();
+            open_in_file_manager(dir.as_os_str())
+        };
+
+        if let Err(e) = result {
+            self.notice = Some(format!("Could not open file manager: {}", e));
+        }
+    }
+
+    /// Reveals the folder holding the analytics history file.
+    #[cfg(feature = "tui")]
+    fn open_history_location(&mut self) {
+        let Some(dir) = self.history.get_path().and_then(Path::parent) else {
+            self.notice = Some("No history location; set history_path or PIPER_DATA_DIR".to_string());
+            return;
+        };
+        if let Err(e) = open_in_file_manager(dir.as_os_str()) {
+            self.notice = Some(format!("Could not open file manager: {}", e));
+        }
+    }
+
+    /// Puts the selected item's path on the system clipboard.
+    #[cfg(feature = "tui")]
+    fn copy_path(&mut self) {
+        let Some(path) = self.selected_item().map(|i| self.items[i].path.clone()) else { return; };
+
+        match copy_to_clipboard(&path.to_string_lossy()) {
+            Ok(()) => self.flash(format!("Copied {}", path.display())),
+            // e.g. over SSH or without X/Wayland: show it so it can be copied by hand
+            Err(_) => self.notice = Some(format!("No clipboard available; path: {}", path.display())),
+        }
+    }
+
+    /// Flips between size and age ordering, keeping the cursor on the same item.
+    #[cfg(feature = "tui")]
+    pub fn toggle_sort(&mut self) {
+        // Background jobs address items by index, so don't reorder under them
+        if self.is_scanning || self.is_compressing || self.is_restoring { return; }
+
+        self.sort_order = match self.sort_order {
+            SortOrder::Size => SortOrder::Age,
+            SortOrder::Age => SortOrder::Size,
+        };
+        self.apply_sort();
+    }
+
+    fn apply_sort(&mut self) {
+        let current = self.selected_item().map(|i| self.items[i].path.clone());
+
+        match self.sort_order {
+            SortOrder::Size => self.items.sort_by_key(|i| std::cmp::Reverse(i.original_size)),
+            SortOrder::Age => self.items.sort_by_key(|i| std::cmp::Reverse(i.age_days.unwrap_or(0))),
+        }
+
+        if let Some(path) = current {
+            let idx = self.items.iter().position(|i| i.path == path).unwrap_or(0);
+            self.select_item(idx);
+        }
+    }
+
+    /// Scanner columns for the current view, in order.
+    #[cfg(feature = "tui")]
+    pub fn visible_columns(&self) -> &[Column] {
+        match self.list_view {
+            ListView::Custom if !self.columns.is_empty() => &self.columns,
+            view => view.columns(),
+        }
+    }
+
+    /// `m`: the built-in views, then the configured `columns` if there are any.
+    #[cfg(feature = "tui")]
+    fn cycle_list_view(&mut self) {
+        self.list_view = match self.list_view {
+            ListView::Detailed if !self.columns.is_empty() => ListView::Custom,
+            view => view.next(),
+        };
+    }
+
+    /// Table rows in display order. Grouped, each project gets a header
+    /// followed by its items (unless collapsed); biggest projects first,
+    /// items outside any project last. Items keep the current sort within a section.
+    /// With `hide_completed`, Done and Deleted items (and sections left empty) are skipped.
+    pub fn rows(&self) -> Vec<ListRow> {
+        let shown = self.items.iter().enumerate()
+            .filter(|(_, item)| !(self.hide_completed && item.is_completed()));
+        if !self.group_by_project {
+            return shown.map(|(i, _)| ListRow::Item(i)).collect();
+        }
+
+        let mut sections: Vec<(Option<PathBuf>, Vec<usize>, u64)> = Vec::new();
+        let mut index: HashMap<&Option<PathBuf>, usize> = HashMap::new();
+        for (i, item) in shown {
+            let s = *index.entry(&item.project).or_insert_with(|| {
+                sections.push((item.project.clone(), Vec::new(), 0));
+                sections.len() - 1
+            });
+            sections[s].1.push(i);
+            sections[s].2 += item.original_size;
+        }
+        sections.sort_by_key(|(root, _, size)| (root.is_none(), std::cmp::Reverse(*size)));
+
+        let mut rows = Vec::with_capacity(self.items.len() + sections.len());
+        for (root, members, size) in sections {
+            let collapsed = self.collapsed_projects.contains(&root);
+            rows.push(ListRow::Project(root, members.len(), size, collapsed));
+            if !collapsed {
+                rows.extend(members.into_iter().map(ListRow::Item));
+            }
+        }
+        rows
+    }
+
+    /// Index into `items` of the row under the cursor; `None` on a section header.
+    pub fn selected_item(&self) -> Option<usize> {
+        match self.rows().get(self.list_state.selected()?)? {
+            ListRow::Item(i) => Some(*i),
+            ListRow::Project(..) => None,
+        }
+    }
+
+    /// The project whose header is under the cursor.
+    #[cfg(feature = "tui")]
+    fn selected_project(&self) -> Option<Option<PathBuf>> {
+        match self.rows().get(self.list_state.selected()?)? {
+            ListRow::Project(root, ..) => Some(root.clone()),
+            ListRow::Item(_) => None,
+        }
+    }
+
+    /// Moves the cursor to the row showing `items[idx]`, if it is visible.
+    fn select_item(&mut self, idx: usize) {
+        let row = self.rows().iter().position(|r| *r == ListRow::Item(idx)).unwrap_or(0);
+        self.list_state.select(Some(row));
+    }
+
+    /// Switches between the flat list and per-project sections, keeping the cursor on the same item.
+    #[cfg(feature = "tui")]
+    pub fn toggle_grouping(&mut self) {
+        let current = self.selected_item();
+        self.group_by_project = !self.group_by_project;
+        self.visual_anchor = None;
+        match current {
+            Some(idx) => self.select_item(idx),
+            None => self.list_state.select(Some(0)),
+        }
+    }
+
+    /// Shows or hides Done and Deleted items, keeping the cursor on the same
+    /// item when it stays visible.
+    #[cfg(feature = "tui")]
+    pub fn toggle_hide_completed(&mut self) {
+        let current = self.selected_item();
+        let row = self.list_state.selected();
+        self.hide_completed = !self.hide_completed;
+        self.visual_anchor = None;
+        match current.and_then(|idx| self.rows().iter().position(|r| *r == ListRow::Item(idx))) {
+            Some(row) => self.list_state.select(Some(row)),
+            // Its row is gone: stay put and let settle_selection clamp
+            None => self.list_state.select(row.or(Some(0))),
+        }
+
+        let hidden = self.items.iter().filter(|i| i.is_completed()).count();
+        self.flash(if self.hide_completed {
+            format!("Hiding {} completed item(s); [H] shows them again", hidden)
+        } else {
+            "Showing all items".to_string()
+        });
+    }
+
+    /// Shows `message` in the footer for `STATUS_MESSAGE_TTL`. For
+    /// confirmations; problems go in `notice`, which waits for a key.
+    pub fn flash(&mut self, message: impl Into<String>) {
+        self.status_message = Some((message.into(), Instant::now()));
+    }
+
+    /// Folds or unfolds the section under the cursor.
+    #[cfg(feature = "tui")]
+    fn toggle_collapsed(&mut self) {
+        let Some(root) = self.selected_project() else { return; };
+        if !self.collapsed_projects.remove(&root) {
+            self.collapsed_projects.insert(root);
+        }
+    }
+
+    /// Space on an item flips it; on a section header it selects the whole
+    /// project, or clears it if everything in it is selected already.
+    #[cfg(feature = "tui")]
     pub fn toggle_selection(&mut self) {
-        if let Some(i) = self.list_state.selected() {
-            if i < self.items.len() {
-                self.items[i].selected = !self.items[i].selected;
+        if let Some(i) = self.selected_item() {
+            self.items[i].selected = !self.items[i].selected;
+        } else if let Some(root) = self.selected_project() {
+            let select = !self.items.iter().filter(|i| i.project == root).all(|i| i.selected);
+            for item in self.items.iter_mut().filter(|i| i.project == root) {
+                item.selected = select;
             }
         }
     }
 
-    // pub fn next_tab(&mut self) { ... } // Removed
+    /// Selects every item, or clears the selection if everything is selected already.
+    #[cfg(feature = "tui")]
+    pub fn toggle_select_all(&mut self) {
+        let select = !self.items.iter().all(|i| i.selected);
+        for item in &mut self.items {
+            item.selected = select;
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    pub fn previous_tab(&mut self) {
+        self.current_tab = match self.current_tab {
+            AppTab::Scanner => AppTab::Problems,
+            AppTab::Analytics => AppTab::Scanner,
+            AppTab::Status => AppTab::Analytics,
+            AppTab::Problems => AppTab::Status,
+        };
+    }
+
+    #[cfg(feature = "tui")]
+    pub fn next_tab(&mut self) {
+        self.current_tab = match self.current_tab {
+            AppTab::Scanner => AppTab::Analytics,
+            AppTab::Analytics => AppTab::Status,
+            AppTab::Status => AppTab::Problems,
+            AppTab::Problems => AppTab::Scanner,
+        };
+    }
+
+    /// Threads the compress/restore pool will use (see `build_pool`).
+    pub fn worker_threads(&self) -> usize {
+        pool_size(self.max_jobs, self.max_open_files)
+    }
+
+    /// (count, total size) of the items marked with Space.
+    #[cfg(feature = "tui")]
+    pub fn selection_summary(&self) -> (usize, u64) {
+        self.items.iter()
+            .filter(|i| i.selected)
+            .fold((0, 0), |(n, size), i| (n + 1, size + i.original_size))
+    }
+
+    /// Bytes the listed items take up right now: compressed size where there
+    /// is one, nothing for deleted items. Files listed inside a listed folder
+    /// (after `x`) count once, as part of the folder.
+    #[cfg(feature = "tui")]
+    pub fn current_footprint(&self) -> u64 {
+        let listed: Vec<Target> = self.items.iter().enumerate()
+            .filter(|(_, item)| item.status != FileStatus::Deleted)
+            .map(|(i, item)| (i, item.path.clone()))
+            .collect();
+        let (outer, _) = split_nested(listed);
+        outer.iter()
+            .map(|(i, _)| self.items[*i].compressed_size.unwrap_or(self.items[*i].original_size))
+            .sum()
+    }
+
+    /// (bytes consumed, bytes total) for the running compression batch.
+    #[cfg(feature = "tui")]
+    pub fn compress_progress(&self) -> (u64, u64) {
+        (self.compress_bytes.values().sum(), self.compress_total)
+    }
+
+    /// (bytes done, size) of one item in the running batch.
+    #[cfg(feature = "tui")]
+    pub fn item_progress(&self, idx: usize) -> (u64, u64) {
+        let size = self.items.get(idx).map_or(0, |i| i.original_size);
+        (self.compress_bytes.get(&idx).copied().unwrap_or_default().min(size), size)
+    }
+
+    /// (files archived, files total) across directories in the running batch,
+    /// or `None` when no directory has reported yet.
+    #[cfg(feature = "tui")]
+    pub fn archive_progress(&self) -> Option<(u64, u64)> {
+        if self.compress_files.is_empty() {
+            return None;
+        }
+        Some(self.compress_files.values().fold((0, 0), |(a, t), (added, total)| (a + added, t + total)))
+    }
+
+    /// Which setting decided `worker_threads`, for the Status tab.
+    #[cfg(feature = "tui")]
+    pub fn worker_limit(&self) -> &'static str {
+        if pool_size(self.max_jobs, usize::MAX) > self.worker_threads() {
+            "max_open_files"
+        } else if self.max_jobs.is_some() {
+            "max_jobs"
+        } else {
+            "all cores"
+        }
+    }
 
+    #[cfg(feature = "tui")]
     pub fn toggle_details(&mut self) {
-        if !self.items.is_empty() {
-             self.show_details = !self.show_details;
+        if self.selected_item().is_none() {
+            return;
+        }
+        self.show_details = !self.show_details;
+        self.backend_estimate = None;
+
+        // Only sources still on disk can be sampled
+        if self.show_details
+            && let Some(i) = self.selected_item()
+            && self.items[i].status == FileStatus::Found
+        {
+            let estimate = compressor::estimate_best_backend(&self.items[i].path, &self.compress_options());
+            self.backend_estimate = Some((i, estimate.map_err(|e| e.to_string())));
         }
     }
 
+    #[cfg(feature = "tui")]
     pub fn next(&mut self) {
         let i = match self.list_state.selected() {
             Some(i) => {
-                if i >= self.items.len().saturating_sub(1) {
+                if i >= self.rows().len().saturating_sub(1) {
                     0
                 } else {
                     i + 1
@@ -165,11 +912,12 @@ impl App {
         self.list_state.select(Some(i));
     }
 
+    #[cfg(feature = "tui")]
     pub fn previous(&mut self) {
         let i = match self.list_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.items.len().saturating_sub(1)
+                    self.rows().len().saturating_sub(1)
                 } else {
                     i - 1
                 }
@@ -179,72 +927,169 @@ impl App {
         self.list_state.select(Some(i));
     }
 
+    /// Sets the refresh cadence, clamped to `TICK_RATE_RANGE_MS`.
+    pub fn set_tick_rate_ms(&mut self, ms: u64) {
+        let (min, max) = TICK_RATE_RANGE_MS;
+        self.tick_rate = Duration::from_millis(ms.clamp(min, max));
+    }
+
+    /// Advances the spinner and applies finished background work. Called once
+    /// per `tick_rate`, independent of how often keys arrive.
     pub fn tick(&mut self) {
-        if self.is_scanning || self.is_compressing {
-            self.spinner_state = (self.spinner_state + 1) % 4;
-            
-            // Check for results
-            let mut messages = Vec::new();
-            if let Some(rx) = &self.rx {
-                while let Ok(msg) = rx.try_recv() {
-                    messages.push(msg);
-                }
-            }
+        if let Some((_, shown)) = &self.status_message
+            && shown.elapsed() >= STATUS_MESSAGE_TTL
+        {
+            self.status_message = None;
+        }
+
+        if self.is_scanning || self.is_compressing || self.is_restoring {
+            self.spinner_state = ((self.started.elapsed().as_millis() / SPINNER_FRAME_MS) % 4) as u8;
+        }
 
+        // Always drained, busy or not: a job can finish after another one
+        // started, or after its own flag was cleared
+        let messages = self.worker.drain();
+        if !messages.is_empty() {
             for msg in messages {
                 match msg {
-                    AppMessage::ScanComplete(items) => {
+                    AppMessage::ScanComplete(items, warnings, visited) => {
+                        let elapsed = self.scan_started.map(|t| t.elapsed()).unwrap_or_default();
+                        self.scan_stats = Some((visited, elapsed));
                         self.items = items;
+                        for warning in &warnings {
+                            self.problems.push(Problem {
+                                severity: Severity::Warning,
+                                path: self.scan_path.clone(),
+                                reason: "Scan incomplete".to_string(),
+                                message: Some(warning.clone()),
+                            });
+                        }
+                        self.scan_warnings = warnings;
+                        if self.sort_order != SortOrder::Size {
+                            self.apply_sort();
+                        }
+                        self.update_projection();
                         self.is_scanning = false;
-                        self.rx = None;
+                        self.has_scanned = true;
                         if !self.items.is_empty() {
                             self.list_state.select(Some(0));
                         }
+                        if let Some(reselect) = self.refresh.take() {
+                            self.reselect(reselect);
+                        }
+                        let count = self.items.len();
+                        self.flash(format!("Scan complete: {} candidate{}", count, if count == 1 { "" } else { "s" }));
+                    }
+                    AppMessage::ExpandComplete(idx, children) => {
+                        // Skip files that are already listed (e.g. expanded twice)
+                        let children: Vec<FileItem> = children.into_iter()
+                            .filter(|c| !self.items.iter().any(|i| i.path == c.path))
+                            .collect();
+                        let insert_at = (idx + 1).min(self.items.len());
+                        self.flash(format!("Expanded: {} large file{} listed", children.len(),
+                            if children.len() == 1 { "" } else { "s" }));
+                        self.items.splice(insert_at..insert_at, children);
+                        self.update_projection();
+                        self.is_scanning = false;
+                    }
+                    AppMessage::SizeComplete(idx, stats) => {
+                        self.is_scanning = false;
+                        self.resizing = None;
+                        match stats {
+                            Some(stats) if idx < self.items.len() => {
+                                let item = &mut self.items[idx];
+                                let before = std::mem::replace(&mut item.original_size, stats.size);
+                                if item.file_count.is_some() {
+                                    item.file_count = Some(stats.files);
+                                }
+                                self.update_projection();
+                                self.flash(format!("Size updated: {} -> {}", format_size(before), format_size(stats.size)));
+                            }
+                            None if idx < self.items.len() => self.mark_vanished(idx),
+                            _ => {}
+                        }
+                    }
+                    AppMessage::CompressionBytes(idx, bytes) => {
+                        self.compress_bytes.insert(idx, bytes);
+                    }
+                    AppMessage::CompressionFiles(idx, added, total) => {
+                        self.compress_files.insert(idx, (added, total));
                     }
                     AppMessage::CompressionProgress(idx, result) => {
                         if idx < self.items.len() {
+                            self.compress_bytes.insert(idx, self.items[idx].original_size);
                             match result {
+                                Ok(stats) if stats.skipped.is_some() => {
+                                    self.items[idx].status = FileStatus::Skipped;
+                                    self.items[idx].reason = stats.skipped.unwrap_or_default();
+                                    self.report(idx, Severity::Warning, None);
+                                },
                                 Ok(stats) => {
                                     self.items[idx].compressed_size = Some(stats.compressed_size);
                                     if stats.original_size > stats.compressed_size {
                                         self.items[idx].status = FileStatus::Done;
+                                        if stats.retries > 0 {
+                                            self.items[idx].reason = format!("Done after {} I/O retr{}", stats.retries,
+                                                if stats.retries == 1 { "y" } else { "ies" });
+                                        }
+                                        self.last_batch.push((self.items[idx].path.clone(), stats.output_path));
                                         self.total_savings += stats.original_size - stats.compressed_size;
+                                        self.batch_savings += stats.original_size - stats.compressed_size;
                                     } else {
                                         // No savings or size increased, mark as Error
                                         self.items[idx].status = FileStatus::Error;
                                         self.items[idx].reason = "No savings or size increased".to_string();
+                                        self.report(idx, Severity::Warning, None);
                                     }
                                 },
-                                Err(_) => {
+                                Err(e) => {
                                     self.items[idx].status = FileStatus::Error;
+                                    self.items[idx].reason = e.clone();
+                                    self.problems.push(Problem {
+                                        severity: Severity::Error,
+                                        path: self.items[idx].path.clone(),
+                                        reason: "Compression failed".to_string(),
+                                        message: Some(e),
+                                    });
                                 }
                             }
                             self.calculate_score();
+                            self.update_projection();
                         }
                     }
                     AppMessage::CompressionDone => {
                         self.is_compressing = false;
-                        self.rx = None;
+                        self.record_run();
+                        self.compression_summary = Some(self.summarize_batch());
                     }
-                    AppMessage::RestorationDone(idx, success) => {
-                        if idx < self.items.len() && success {
+                    AppMessage::RestorationDone(idx, result) => {
+                        if idx < self.items.len() && result.is_ok() {
                             self.items[idx].status = FileStatus::Restored;
                             // Revert Stats
-                            if let Some(compressed) = self.items[idx].compressed_size {
-                                if self.items[idx].original_size > compressed {
-                                    self.total_savings = self.total_savings.saturating_sub(self.items[idx].original_size - compressed);
-                                }
+                            if let Some(compressed) = self.items[idx].compressed_size
+                                && self.items[idx].original_size > compressed
+                            {
+                                self.total_savings = self.total_savings.saturating_sub(self.items[idx].original_size - compressed);
                             }
                             self.items[idx].compressed_size = None;
                             self.calculate_score();
                         } else if idx < self.items.len() {
                              self.items[idx].status = FileStatus::Error;
+                             self.problems.push(Problem {
+                                 severity: Severity::Error,
+                                 path: self.items[idx].path.clone(),
+                                 reason: "Restore failed".to_string(),
+                                 message: result.err(),
+                             });
                         }
+                    }
+                    AppMessage::RestorationFinished => {
                         self.is_restoring = false;
-                        self.rx = None;
+                        self.flash("Restore finished");
                     }
                 }
             }
+            self.settle_selection();
         }
     }
 
@@ -260,155 +1105,1346 @@ impl App {
         }
     }
 
+    /// Rough savings still on the table for items that haven't been processed.
+    fn update_projection(&mut self) {
+        self.projected_savings = self.items.iter()
+            .filter(|i| i.status == FileStatus::Found)
+            .map(|i| self.estimator.estimate(&i.path, i.original_size))
+            .sum();
+    }
+
     fn start_scan(&mut self) {
         if self.is_scanning || self.is_compressing { return; }
+
+        // A missing root would otherwise crawl nothing and look like "no artifacts"
+        self.scan_error = check_scan_path(&self.scan_path);
+        if self.scan_error.is_some() {
+            return;
+        }
+
+        // Piper deletes originals; make sure `/` or `~` is really what was meant
+        if !self.broad_scan_confirmed && is_dangerous_root(&self.scan_path) {
+            self.confirm_broad_scan = true;
+            return;
+        }
+
+        if let Err(e) = self.recent.record(&self.scan_path) {
+            self.notice = Some(format!("Could not save recent scans: {:#}", e));
+        }
+
         self.is_scanning = true;
+        self.scan_started = Some(Instant::now());
         self.items.clear(); 
+        self.scan_warnings.clear();
         self.weissman_score = 0.0;
         self.total_savings = 0;
-
-        let (tx, rx): (Sender<AppMessage>, Receiver<AppMessage>) = mpsc::channel();
-        self.rx = Some(rx);
+        self.projected_savings = 0;
 
         let scan_root = self.scan_path.clone();
+        let exclude = self.exclude.clone();
+        let exclude_extensions = self.exclude_extensions.clone();
+        let skip_dirs = self.skip_dirs.clone();
+        let big_file_threshold = self.big_file_threshold;
+        let time_basis = self.time_basis;
+        let naming = self.naming.clone();
+        let protect_recent = self.protect_recent;
 
-        thread::spawn(move || {
-            let mut results = Vec::new();
+        self.worker.spawn_detached(move |job| {
             // Spyder V2: Parallel Crawl
-            let spyder = Spyder::new(scan_root);
-            let scan_res = spyder.crawl();
-                 
-            for res in scan_res {
-                results.push(FileItem {
-                    path: res.path.to_string_lossy().to_string(),
-                    original_size: res.size,
-                    compressed_size: None,
-                    status: FileStatus::Found,
-                    reason: res.reason,
-                    selected: false,
-                });
-            }
-            let _ = tx.send(AppMessage::ScanComplete(results));
+            let spyder = Spyder::new(&scan_root)
+                .exclude(exclude)
+                .exclude_extensions(exclude_extensions)
+                .skip_dirs(skip_dirs)
+                .big_files(big_file_threshold)
+                .time_basis(time_basis)
+                .naming(naming)
+                .protect_recent(protect_recent);
+            let report = spyder.crawl();
+            let warnings = report.skipped_summary();
+            let results = report.items.into_iter()
+                .map(|res| FileItem::in_project(res, &scan_root))
+                .collect();
+            job.send(AppMessage::ScanComplete(results, warnings, report.visited));
         });
     }
 
-    fn start_compression(&mut self) {
-        if self.is_scanning || self.is_compressing { return; }
-        self.is_compressing = true;
+    /// Runs the last scan again (same root and settings), keeping the
+    /// selection and cursor on items that are still there.
+    #[cfg(feature = "tui")]
+    fn rescan(&mut self) {
+        if self.is_scanning || self.is_compressing || self.is_restoring { return; }
+        if !self.has_scanned {
+            self.notice = Some("Nothing to refresh yet; press S to scan".to_string());
+            return;
+        }
 
-        let (tx, rx): (Sender<AppMessage>, Receiver<AppMessage>) = mpsc::channel();
-        self.rx = Some(rx);
+        self.refresh = Some(Reselect {
+            selected: self.items.iter().filter(|i| i.selected).map(|i| i.path.clone()).collect(),
+            cursor: self.selected_item().map(|i| self.items[i].path.clone()),
+        });
+        self.start_scan();
+        if !self.is_scanning {
+            self.refresh = None;
+        }
+    }
 
-        // Collect files to compress
-        // Logic: If any items are selected, compress ONLY selected. Else, compress ALL found.
-        let has_selection = self.items.iter().any(|i| i.selected);
+    /// Reapplies a selection saved by `rescan` to the fresh scan results.
+    fn reselect(&mut self, reselect: Reselect) {
+        for item in &mut self.items {
+            item.selected = reselect.selected.contains(&item.path);
+        }
+        if let Some(idx) = reselect.cursor.and_then(|path| self.items.iter().position(|i| i.path == path)) {
+            self.select_item(idx);
+        }
+    }
 
-        let targets: Vec<(usize, PathBuf)> = self.items.iter().enumerate()
-            .filter(|(_, item)| item.status == FileStatus::Found)
-            .filter(|(_, item)| !has_selection || item.selected)
-            .map(|(i, item)| (i, PathBuf::from(&item.path)))
-            .collect();
+    /// Measures the selected item again, without a full rescan: its size
+    /// can drift between the scan and acting on it (a build adds to `target`).
+    #[cfg(feature = "tui")]
+    fn resize_item(&mut self) {
+        if self.is_scanning || self.is_compressing || self.is_restoring { return; }
 
-        // Mark them as compressing in UI immediately
-        for (i, _) in &targets {
-            self.items[*i].status = FileStatus::Compressing;
+        let Some(i) = self.selected_item() else { return; };
+        if self.items[i].status != FileStatus::Found {
+            return;
         }
 
-        let compression_level = self.compression_level;
+        let path = self.items[i].path.clone();
+        self.is_scanning = true;
+        self.resizing = Some(path.clone());
 
-        thread::spawn(move || {
-            // Parallel Compression using Rayon
-            targets.into_par_iter().for_each_with((tx.clone(), compression_level), |(s, level), (idx, path)| {
-                let res = compressor::compress_file(&path, *level).map_err(|e| e.to_string());
-                let _ = s.send(AppMessage::CompressionProgress(idx, res));
-            });
-            
-            let _ = tx.send(AppMessage::CompressionDone);
+        self.worker.spawn_detached(move |job| {
+            let stats = match std::fs::metadata(&path) {
+                Ok(m) if m.is_dir() => Some(spyder::dir_stats(&path)),
+                Ok(m) => Some(spyder::DirStats { size: m.len(), files: 1, modified: m.modified().ok() }),
+                Err(_) => None,
+            };
+            job.send(AppMessage::SizeComplete(i, stats));
         });
     }
 
-    fn delete_item(&mut self) {
-        let has_selection = self.items.iter().any(|i| i.selected);
-        
-        let indices: Vec<usize> = if has_selection {
-            self.items.iter().enumerate()
-                .filter(|(_, i)| i.selected)
-                .map(|(idx, _)| idx)
-                .collect()
-        } else {
-             if let Some(i) = self.list_state.selected() {
-                 vec![i]
-             } else {
-                 vec![]
-             }
-        };
+    /// Breaks a heavy directory candidate down into its large files,
+    /// listed right below it so they can be selected individually.
+    #[cfg(feature = "tui")]
+    fn expand_item(&mut self) {
+        if self.is_scanning || self.is_compressing || self.is_restoring { return; }
 
-        for i in indices {
-            if i < self.items.len() {
-                 let path = PathBuf::from(&self.items[i].path);
-                 // Only delete if it exists (or if we think it exists)
-                 // trash::delete handles non-existence nicely? 
-                 // It returns error if file doesn't exist.
-                 if path.exists() {
-                     match trash::delete(&path) {
-                         Ok(_) => {
-                             self.items[i].status = FileStatus::Deleted;
-                             // Treat deletion as 100% savings for the score
-                             self.items[i].compressed_size = Some(0);
-                             self.total_savings += self.items[i].original_size;
-                         }
-                         Err(_) => {
-                             self.items[i].status = FileStatus::Error;
-                         }
-                     }
-                 }
-            }
+        let Some(i) = self.selected_item() else { return; };
+        if self.items[i].status != FileStatus::Found {
+            return;
         }
-        self.calculate_score();
+
+        let dir = self.items[i].path.clone();
+        if !dir.is_dir() {
+            return;
+        }
+
+        self.is_scanning = true;
+        let exclude_extensions = self.exclude_extensions.clone();
+        let skip_dirs = self.skip_dirs.clone();
+        let scan_root = self.scan_path.clone();
+        let time_basis = self.time_basis;
+        let naming = self.naming.clone();
+
+        self.worker.spawn_detached(move |job| {
+            let children = Spyder::new(dir)
+                .exclude_extensions(exclude_extensions)
+                .skip_dirs(skip_dirs)
+                .time_basis(time_basis)
+                .naming(naming)
+                .large_files()
+                .into_iter()
+                .map(|res| FileItem::in_project(res, &scan_root))
+                .collect();
+            job.send(AppMessage::ExpandComplete(i, children));
+        });
     }
 
+    /// Replaces the selection with the `n` biggest uncompressed candidates,
+    /// whatever the sort order. Returns how many were selected.
+    pub fn select_largest(&mut self, n: usize) -> usize {
+        let mut found: Vec<usize> = (0..self.items.len())
+            .filter(|&i| self.items[i].status == FileStatus::Found)
+            .collect();
+        found.sort_by_key(|&i| std::cmp::Reverse(self.items[i].original_size));
+        found.truncate(n);
 
-    fn restore_item(&mut self) {
-        if self.is_scanning || self.is_compressing || self.is_restoring { return; }
+        for item in &mut self.items {
+            item.selected = false;
+        }
+        for &i in &found {
+            self.items[i].selected = true;
+        }
+        found.len()
+    }
+
+    /// `--top N` without the TUI: scans, compresses the `n` largest
+    /// candidates, and returns once that batch is done. There is no prompt
+    /// for broad roots; the caller warns instead. A `dry_run` only selects
+    /// them and records the projected savings as a simulated history entry.
+    pub fn compress_top(&mut self, n: usize, dry_run: bool) {
+        // Items loaded with `load_listed` stand in for the scan
+        if !self.has_scanned {
+            self.broad_scan_confirmed = true;
+            self.start_scan();
+            self.wait_until_idle();
+        }
+        if self.select_largest(n) == 0 {
+            return;
+        }
+        if dry_run {
+            let (count, _, projected) = self.compression_plan();
+            if let Err(e) = self.history.add_simulated(projected, count, self.compression_level, self.backend.name()) {
+                self.notice = Some(format!("Could not save history: {:#}", e));
+            }
+        } else {
+            self.start_compression();
+            self.wait_until_idle();
+        }
+    }
+
+    /// Fills the list from `spyder::measure_paths` instead of a scan, and
+    /// goes straight to it.
+    pub fn load_listed(&mut self, report: spyder::ScanReport) {
+        self.scan_warnings = report.skipped_summary();
+        self.items = report.items.into_iter()
+            .map(|res| FileItem::in_project(res, &self.scan_path))
+            .collect();
+        self.has_scanned = true;
+        self.scan_error = None; // The root wasn't needed
+        self.view = AppView::Dashboard;
+        if !self.items.is_empty() {
+            self.list_state.select(Some(0));
+        }
+        self.update_projection();
+    }
+
+    fn wait_until_idle(&mut self) {
+        while self.is_scanning || self.is_compressing {
+            thread::sleep(self.tick_rate.min(Duration::from_millis(50)));
+            self.tick();
+        }
+    }
+
+    /// Workers for a batch with `opts`, cut (and the level capped, as a last
+    /// resort) so that their encoders fit in `memory_fraction` of RAM. Says so
+    /// in the footer when it had to cut.
+    fn memory_safe_jobs(&mut self, opts: &mut CompressOptions) -> usize {
+        let jobs = self.worker_threads();
+        let Some(total) = self.total_memory else { return jobs };
+        let fit = memory::fit_options(jobs, opts, total, self.memory_fraction);
+        let peak = compressor::peak_level(opts);
+
+        let mib = memory::per_job(peak, opts.backend) / (1024 * 1024);
+        if fit.max_level < peak {
+            opts.max_level = fit.max_level;
+            self.notice = Some(format!(
+                "Level {} needs ~{} MB per job, more than {:.0}% of RAM; using level {} on 1 job",
+                peak, mib, self.memory_fraction * 100.0, fit.max_level,
+            ));
+        } else if fit.jobs < jobs {
+            self.notice = Some(format!(
+                "Level {} needs ~{} MB per job; running {} jobs instead of {} to stay within {:.0}% of RAM",
+                peak, mib, fit.jobs, jobs, self.memory_fraction * 100.0,
+            ));
+        }
+        fit.jobs
+    }
+
+    fn compression_targets(&self) -> Vec<Target> {
+        // Logic: If any items are selected, compress ONLY selected. Else, compress ALL found.
+        let has_selection = self.items.iter().any(|i| i.selected);
+        self.items.iter().enumerate()
+            .filter(|(_, item)| item.status == FileStatus::Found)
+            .filter(|(_, item)| !has_selection || item.selected)
+            .map(|(i, item)| (i, item.path.clone()))
+            .collect()
+    }
+
+    /// (items, total size, estimated bytes reclaimed) that `c` would process,
+    /// for the confirmation popup. Items inside another target count once.
+    pub fn compression_plan(&self) -> (usize, u64, u64) {
+        let (targets, _) = split_nested(self.compression_targets());
+        targets.iter().fold((0, 0, 0), |(n, size, saved), (i, path)| {
+            let item = &self.items[*i];
+            (n + 1, size + item.original_size, saved + self.estimator.estimate(path, item.original_size))
+        })
+    }
+
+    fn start_compression(&mut self) {
+        if self.is_scanning || self.is_compressing { return; }
+        self.is_compressing = true;
+
+        let targets = self.compression_targets();
+
+        // A folder's archive already contains anything selected inside it
+        let (mut targets, nested) = split_nested(targets);
+        for (i, parent) in nested {
+            self.items[i].status = FileStatus::Skipped;
+            self.items[i].reason = format!("Included in {} archive", parent.display());
+        }
+        // Largest first, so the longest jobs don't start last and leave one worker busy
+        targets.sort_by_key(|(i, _)| std::cmp::Reverse(self.items[*i].original_size));
+        self.compress_batch = targets.iter().map(|(i, _)| *i).collect();
+
+        self.last_batch.clear();
+        self.batch_savings = 0;
+        self.batch_level = self.compression_level;
+        self.compress_started = Some(Instant::now());
+        self.compress_bytes.clear();
+        self.compress_files.clear();
+        self.compress_total = targets.iter().map(|(i, _)| self.items[*i].original_size).sum();
+
+        // Mark them as compressing in UI immediately
+        for (i, _) in &targets {
+            self.items[*i].status = FileStatus::Compressing;
+        }
+
+        let mut opts = self.compress_options();
+        let jobs = self.memory_safe_jobs(&mut opts);
+        self.batch_level = self.compression_level.min(opts.max_level);
+        let pool = build_pool(Some(jobs), self.max_open_files);
+
+        self.worker.spawn(move |job| {
+            // Parallel Compression using Rayon, bounded by max_jobs. Bridged
+            // so free workers take the next target in order, not a split half.
+            pool.install(|| {
+                targets.into_iter().par_bridge().for_each_with((job.clone(), opts), |(s, opts), (idx, path)| {
+                    if s.stopping() {
+                        return;
+                    }
+                    // One message per step, not per buffer, so the channel isn't flooded
+                    let sender = &*s;
+                    let reported = AtomicU64::new(0);
+                    let progress = |update: ProgressUpdate| match update {
+                        ProgressUpdate::Bytes(total) => {
+                            if total - reported.load(Ordering::Relaxed) >= PROGRESS_STEP {
+                                reported.store(total, Ordering::Relaxed);
+                                sender.send(AppMessage::CompressionBytes(idx, total));
+                            }
+                        }
+                        ProgressUpdate::Files { added, total } => {
+                            if added % FILES_STEP == 0 || added == total {
+                                sender.send(AppMessage::CompressionFiles(idx, added, total));
+                            }
+                        }
+                    };
+                    // With its causes: the stage context alone doesn't say what broke
+                    let res = compressor::compress_with_progress(&path, opts, Some(&progress))
+                        .map_err(|e| format!("{:#}", e));
+                    s.send(AppMessage::CompressionProgress(idx, res));
+                });
+            });
+            
+            job.send(AppMessage::CompressionDone);
+        });
+    }
+
+    /// How the batch just finished went, counted from its items' statuses.
+    fn summarize_batch(&self) -> CompressionSummary {
+        let mut summary = CompressionSummary {
+            saved: self.batch_savings,
+            elapsed: self.compress_started.map(|t| t.elapsed()).unwrap_or_default(),
+            ..Default::default()
+        };
+        for item in self.compress_batch.iter().filter_map(|&i| self.items.get(i)) {
+            match item.status {
+                FileStatus::Done => summary.done += 1,
+                FileStatus::Skipped => summary.skipped += 1,
+                FileStatus::Error => summary.errors += 1,
+                _ => {}
+            }
+        }
+        summary
+    }
+
+    /// Adds the finished batch to the history, if it saved anything.
+    fn record_run(&mut self) {
+        if self.last_batch.is_empty() {
+            return;
+        }
+        let artifacts: Vec<Artifact> = self.last_batch.iter()
+            .filter_map(|(path, _)| self.items.iter().find(|i| &i.path == path))
+            .filter_map(|item| Some(Artifact {
+                path: item.path.clone(),
+                original_size: item.original_size,
+                compressed_size: item.compressed_size?,
+            }))
+            .collect();
+        self.history.add_artifacts(artifacts);
+        if let Err(e) = self.history.add_entry(self.batch_savings, self.last_batch.len(), self.batch_level, self.backend.name()) {
+            self.notice = Some(format!("Could not save history: {:#}", e));
+        }
+    }
+
+    /// Logs item `idx` on the Problems tab under its current `reason`.
+    fn report(&mut self, idx: usize, severity: Severity, message: Option<String>) {
+        self.problems.push(Problem {
+            severity,
+            path: self.items[idx].path.clone(),
+            reason: self.items[idx].reason.clone(),
+            message,
+        });
+    }
+
+    /// A candidate that was deleted outside Piper; not a failure on our side.
+    fn mark_vanished(&mut self, idx: usize) {
+        self.items[idx].status = FileStatus::Skipped;
+        self.items[idx].reason = compressor::VANISHED_REASON.to_string();
+        self.items[idx].selected = false;
+    }
+
+    pub fn compress_options(&self) -> CompressOptions {
+        CompressOptions {
+            level: self.compression_level,
+            backend: self.backend,
+            max_level: compressor::MAX_LEVEL,
+            keep_original: self.keep_original || self.safe_mode,
+            output_dir: self.output_dir.clone(),
+            root: Some(self.scan_path.clone()),
+            backup_dir: self.backup_dir.clone(),
+            adaptive_levels: self.adaptive_levels,
+            level_overrides: self.level_overrides.clone(),
+            dereference_symlinks: self.dereference_symlinks,
+            protected_paths: self.protected_paths.clone(),
+            verify: self.verify,
+            naming: self.naming.clone(),
+            protect_recent: self.protect_recent,
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    fn delete_item(&mut self) {
+        if self.safe_mode {
+            self.notice = Some("Safe mode is on: delete is disabled".to_string());
+            return;
+        }
 
-        if let Some(i) = self.list_state.selected() {
+        for i in self.delete_targets() {
             if i < self.items.len() {
-                // Restoration only makes sense for Compressed (Done) items
-                if self.items[i].status == FileStatus::Done {
-                    self.is_restoring = true;
-                    // Optimistic update
-                    self.items[i].status = FileStatus::Compressing; // Reuse spinner
-
-                    let (tx, rx): (Sender<AppMessage>, Receiver<AppMessage>) = mpsc::channel();
-                    self.rx = Some(rx);
-
-                    let path = PathBuf::from(&self.items[i].path);
-
-                    thread::spawn(move || {
-                        // Decompress
-                        // Decompress
-                        // Check for .tar.zst first (directories)
-                        // If path was "folder", output was "folder.tar.zst"
-                        let tar_zst = path.with_extension("tar.zst");
-                        // If path was "folder.ext", output was "folder.tar.zst" ? No, I constructed it with to_string_lossy.
-                        // In compressor: PathBuf::from(format!("{}.tar.zst", input_path.to_string_lossy()));
-                        // So if path is "folder", it is "folder.tar.zst".
-                        let tar_path = PathBuf::from(format!("{}.tar.zst", path.to_string_lossy()));
-                        
-                        let zst_path = if tar_path.exists() {
-                            tar_path
-                        } else {
-                            path.with_extension(format!("{}.zst", path.extension().unwrap_or_default().to_string_lossy()))
-                        };
-                        
-                        let success = match compressor::decompress_file(&zst_path) {
-                            Ok(_) => true,
-                            Err(_) => false,
-                        };
-                        let _ = tx.send(AppMessage::RestorationDone(i, success));
-                    });
+                 let path = self.items[i].path.clone();
+                 if self.refuse_recent(i) {
+                     continue;
+                 }
+                 // Only delete if it exists (or if we think it exists)
+                 // trash::delete returns an error if the file doesn't exist.
+                 if path.exists() && let Some(dir) = self.backup_dir.clone() {
+                     self.delete_to_backup(i, &dir, &path);
+                 } else if path.exists() {
+                     match trash::delete(&path) {
+                         Ok(_) => {
+                             self.items[i].status = FileStatus::Deleted;
+                             // Treat deletion as 100% savings for the score
+                             self.items[i].compressed_size = Some(0);
+                             self.total_savings += self.items[i].original_size;
+                         }
+                         // Removed by someone else between the check and the trash call
+                         Err(_) if !path.exists() => self.mark_vanished(i),
+                         Err(e) => self.delete_without_trash(i, &path, &e.to_string()),
+                     }
+                 } else if self.items[i].status == FileStatus::Found {
+                     self.mark_vanished(i);
+                 }
+            }
+        }
+        self.calculate_score();
+        self.update_projection();
+    }
+
+    /// The selection if any, else the cursor row.
+    #[cfg(feature = "tui")]
+    fn delete_targets(&self) -> Vec<usize> {
+        if self.items.iter().any(|i| i.selected) {
+            self.items.iter().enumerate()
+                .filter(|(_, i)| i.selected)
+                .map(|(idx, _)| idx)
+                .collect()
+        } else {
+            self.selected_item().into_iter().collect()
+        }
+    }
+
+    /// Marks the item skipped if it changed within `protect_recent`.
+    #[cfg(feature = "tui")]
+    fn refuse_recent(&mut self, idx: usize) -> bool {
+        let refused = self.protect_recent
+            .is_some_and(|window| compressor::recently_modified(&self.items[idx].path, window));
+        if refused {
+            self.items[idx].status = FileStatus::Skipped;
+            self.items[idx].reason = compressor::RECENT_REASON.to_string();
+        }
+        refused
+    }
+
+    /// `Shift-D`: like `d`, but removes the items outright instead of moving
+    /// them to the trash or the backup store, so the space comes back at
+    /// once. Asks for a Shift-Y first; see `purge`.
+    #[cfg(feature = "tui")]
+    fn purge_item(&mut self) {
+        if self.safe_mode {
+            self.notice = Some("Safe mode is on: delete is disabled".to_string());
+            return;
+        }
+        let targets: Vec<usize> = self.delete_targets().into_iter()
+            .filter(|&i| self.items[i].path.symlink_metadata().is_ok())
+            .collect();
+        if !targets.is_empty() {
+            self.pending_purge = Some(targets);
+        }
+    }
+
+    /// Deletes the confirmed `purge_item` targets for good. Links are removed,
+    /// never followed.
+    #[cfg(feature = "tui")]
+    fn purge(&mut self, targets: Vec<usize>) {
+        for i in targets {
+            if i >= self.items.len() || self.refuse_recent(i) {
+                continue;
+            }
+            let path = self.items[i].path.clone();
+            let removed = match path.symlink_metadata() {
+                Ok(m) if m.is_dir() => std::fs::remove_dir_all(&path),
+                Ok(_) => std::fs::remove_file(&path),
+                Err(e) => Err(e),
+            };
+            match removed {
+                Ok(()) => {
+                    self.items[i].status = FileStatus::Deleted;
+                    self.items[i].compressed_size = Some(0);
+                    self.total_savings += self.items[i].original_size;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => self.mark_vanished(i),
+                Err(e) => {
+                    self.items[i].status = FileStatus::Error;
+                    self.items[i].reason = format!("Permanent delete failed: {}", e);
+                    self.report(i, Severity::Error, None);
                 }
             }
         }
+        self.calculate_score();
+        self.update_projection();
+    }
+
+    /// With the backup store on, deletes skip the OS trash so everything
+    /// Piper removed sits in one place with one retention policy.
+    #[cfg(feature = "tui")]
+    fn delete_to_backup(&mut self, idx: usize, dir: &Path, path: &Path) {
+        match backup::move_into(dir, path) {
+            Ok(_) => {
+                self.items[idx].status = FileStatus::Deleted;
+                self.items[idx].compressed_size = Some(0);
+                self.total_savings += self.items[idx].original_size;
+            }
+            Err(e) => {
+                self.items[idx].status = FileStatus::Error;
+                self.items[idx].reason = format!("Backup failed: {:#}", e);
+                self.report(idx, Severity::Error, None);
+            }
+        }
+    }
+
+    /// The OS trash refused (often: no freedesktop trash on a headless box), so
+    /// fall back to `trash_fallback` and say what happened.
+    #[cfg(feature = "tui")]
+    fn delete_without_trash(&mut self, idx: usize, path: &Path, trash_error: &str) {
+        let fallback = match (self.trash_fallback, backup::trash_dir()) {
+            (TrashFallback::PiperTrash, Some(dir)) => {
+                Some(backup::move_into(&backup::session_dir(&dir), path).map(|_| dir))
+            }
+            _ => None,
+        };
+        match fallback {
+            Some(Ok(dir)) => {
+                self.items[idx].status = FileStatus::Deleted;
+                self.items[idx].compressed_size = Some(0);
+                self.total_savings += self.items[idx].original_size;
+                self.notice = Some(format!("System trash unavailable; moved to {}", dir.display()));
+            }
+            Some(Err(e)) => {
+                self.items[idx].status = FileStatus::Error;
+                self.items[idx].reason = format!("Trash unavailable: {:#}", e);
+                self.report(idx, Severity::Error, None);
+            }
+            None => {
+                self.items[idx].status = FileStatus::Error;
+                self.items[idx].reason = format!("Trash unavailable: {}", trash_error);
+                self.report(idx, Severity::Error, None);
+            }
+        }
+    }
+
+    /// Shows what's inside the selected item's archive without extracting it.
+    #[cfg(feature = "tui")]
+    fn preview_item(&mut self) {
+        if let Some(i) = self.selected_item()
+            && self.items[i].status == FileStatus::Done
+        {
+            let zst_path = compressor::find_artifact(&self.items[i].path, &self.compress_options());
+            self.preview = Some(compressor::list_archive(&zst_path, &self.naming).map_err(|e| e.to_string()));
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    fn restore_item(&mut self) {
+        if self.is_scanning || self.is_compressing || self.is_restoring { return; }
+
+        // Same targeting as delete: the selection if any, else the cursor row.
+        // Restoration only makes sense for Compressed (Done) items.
+        let has_selection = self.items.iter().any(|i| i.selected);
+        let cursor = self.selected_item();
+        let targets: Vec<(usize, PathBuf)> = self.items.iter().enumerate()
+            .filter(|(idx, item)| {
+                if has_selection { item.selected } else { cursor == Some(*idx) }
+            })
+            .filter(|(_, item)| item.status == FileStatus::Done)
+            .map(|(idx, item)| (idx, item.path.clone()))
+            .collect();
+
+        if targets.is_empty() {
+            return;
+        }
+
+        let opts = self.compress_options();
+        let targets: Vec<Target> = targets.into_iter()
+            .map(|(idx, path)| (idx, compressor::find_artifact(&path, &opts)))
+            .collect();
+
+        // Ask before writing somewhere other than next to a moved archive
+        let moved = targets.iter()
+            .filter(|(_, artifact)| Sidecar::read(artifact).is_some_and(|s| s.artifact_moved(artifact)))
+            .count();
+        if moved > 0 {
+            self.pending_restore = Some((targets, moved));
+        } else {
+            self.spawn_restore(targets, true);
+        }
+    }
+
+    /// y: restore moved archives to their original location, n: next to the archive.
+    #[cfg(feature = "tui")]
+    fn handle_restore_prompt_input(&mut self, key: KeyEvent) {
+        let to_original = match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => true,
+            KeyCode::Char('n') => false,
+            KeyCode::Esc => {
+                self.pending_restore = None;
+                return;
+            }
+            _ => return,
+        };
+        if let Some((targets, _)) = self.pending_restore.take() {
+            self.spawn_restore(targets, to_original);
+        }
+    }
+
+    /// Decompresses everything the most recent compression run produced.
+    #[cfg(feature = "tui")]
+    fn undo_last_compression(&mut self) {
+        if self.is_scanning || self.is_compressing || self.is_restoring { return; }
+
+        // Match by path: expanding a folder since then shifts the indices
+        let batch = std::mem::take(&mut self.last_batch);
+        let targets: Vec<(usize, PathBuf)> = batch.into_iter()
+            .filter_map(|(path, artifact)| {
+                self.items.iter()
+                    .position(|i| i.path == path && i.status == FileStatus::Done)
+                    .map(|idx| (idx, artifact))
+            })
+            .collect();
+
+        if targets.is_empty() {
+            self.notice = Some("Nothing to undo".to_string());
+            return;
+        }
+        self.spawn_restore(targets, true);
+    }
+
+    /// Decompresses each `(item index, artifact)` pair on the worker pool,
+    /// either to the sidecar's original location or next to the artifact.
+    #[cfg(feature = "tui")]
+    fn spawn_restore(&mut self, targets: Vec<Target>, to_original: bool) {
+        self.is_restoring = true;
+        // Optimistic update
+        for (idx, _) in &targets {
+            self.items[*idx].status = FileStatus::Compressing; // Reuse spinner
+        }
+
+        let pool = build_pool(self.max_jobs, self.max_open_files);
+        let opts = RestoreOptions {
+            keep_archive: self.keep_archive,
+            naming: self.naming.clone(),
+            max_expansion: self.max_expansion,
+            match_archive_mtime: self.match_archive_mtime,
+        };
+        self.worker.spawn(move |job| {
+            // Decompressing many archives at once can thrash a spinning disk,
+            // so this shares the max_jobs cap with compression.
+            pool.install(|| {
+                targets.into_par_iter().for_each_with(job.clone(), |s, (idx, zst_path)| {
+                    if s.stopping() {
+                        return;
+                    }
+                    let result = compressor::decompress_with(&zst_path, to_original, &opts)
+                        .map(|_| ())
+                        .map_err(|e| format!("{:#}", e));
+                    s.send(AppMessage::RestorationDone(idx, result));
+                });
+            });
+
+            job.send(AppMessage::RestorationFinished);
+        });
+    }
+
+    /// Whether a compress or restore job is still running.
+    #[cfg(feature = "tui")]
+    pub fn has_running_workers(&self) -> bool {
+        self.worker.is_busy()
+    }
+
+    /// Before quitting: tells workers to start no new items, then waits up
+    /// to `timeout` for the ones in hand, so no item is cut off between its
+    /// artifact being written and the original removed. What finished is
+    /// recorded as usual (history included). False if some still run.
+    #[cfg(feature = "tui")]
+    pub fn shutdown(&mut self, timeout: Duration) -> bool {
+        let finished = self.worker.shutdown(timeout);
+        self.tick();
+        finished
+    }
+}
+
+/// How long quitting waits for in-flight compress and restore jobs.
+#[cfg(feature = "tui")]
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Terminals disagree on Shift+letter: some send `A`, some `a` with SHIFT.
+/// Fold both into `A` so bindings only need to match the character.
+#[cfg(feature = "tui")]
+fn normalize_key(mut key: KeyEvent) -> KeyEvent {
+    if let KeyCode::Char(c) = key.code
+        && key.modifiers.contains(KeyModifiers::SHIFT)
+    {
+        key.code = KeyCode::Char(c.to_ascii_uppercase());
+        key.modifiers.remove(KeyModifiers::SHIFT);
+    }
+    key
+}
+
+/// Outcome of one compression batch, for the popup shown when it ends.
+#[derive(Debug, Default)]
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub struct CompressionSummary {
+    pub done: usize,
+    pub skipped: usize,
+    pub errors: usize,
+    pub saved: u64,
+    pub elapsed: Duration,
+}
+
+/// Selected paths and the cursor's path, carried across a refresh.
+pub struct Reselect {
+    pub selected: HashSet<PathBuf>,
+    pub cursor: Option<PathBuf>,
+}
+
+/// Winning backend and its stats on a sample, or why sampling failed.
+pub type BackendEstimate = Result<(Backend, CompressionStats), String>;
+
+/// An item index and the path it stands for.
+pub type Target = (usize, PathBuf);
+
+/// Separates targets that live inside another target. Returns the outermost
+/// targets, and each nested one paired with the ancestor that covers it.
+fn split_nested(mut targets: Vec<Target>) -> (Vec<Target>, Vec<Target>) {
+    // Path order is component-wise, so descendants directly follow their ancestor
+    targets.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut outer: Vec<Target> = Vec::new();
+    let mut nested = Vec::new();
+    for (i, path) in targets {
+        match outer.last() {
+            Some((_, root)) if path.starts_with(root) => nested.push((i, root.clone())),
+            _ => outer.push((i, path)),
+        }
+    }
+    (outer, nested)
+}
+
+/// Dedicated pool so `max_jobs` bounds Piper's parallel I/O without touching
+/// the global Rayon pool.
+///
+/// The size is always explicit, so RAYON_NUM_THREADS has no effect here (it
+/// still sizes the global pool the scanner uses). Each job runs a single-threaded
+/// zstd encoder, so `max_jobs` is also the number of busy cores while compressing.
+pub(crate) fn build_pool(max_jobs: Option<usize>, max_open_files: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(pool_size(max_jobs, max_open_files))
+        .build()
+        .unwrap_or_else(|_| rayon::ThreadPoolBuilder::new().build().expect("Failed to build thread pool"))
+}
+
+#[cfg(feature = "tui")]
+fn open_in_file_manager(target: &std::ffi::OsStr) -> std::io::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(target_os = "windows") {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    // Detach: we only care whether the opener could be launched
+    std::process::Command::new(opener)
+        .arg(target)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Pipes `text` into the platform's clipboard tool, trying the usual Linux
+/// ones in turn since which is installed varies.
+#[cfg(feature = "tui")]
+fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let candidates: &[&[&str]] = if cfg!(target_os = "macos") {
+        &[&["pbcopy"]]
+    } else if cfg!(target_os = "windows") {
+        &[&["clip"]]
+    } else {
+        &[&["wl-copy"], &["xclip", "-selection", "clipboard"], &["xsel", "--clipboard", "--input"]]
+    };
+
+    let mut last_err = std::io::Error::from(std::io::ErrorKind::NotFound);
+    for command in candidates {
+        let child = std::process::Command::new(command[0])
+            .args(&command[1..])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                last_err = e;
+                continue;
+            }
+        };
+        // A tool that can't reach a display may exit before reading (BrokenPipe)
+        let written = match child.stdin.take() {
+            Some(mut stdin) => stdin.write_all(text.as_bytes()),
+            None => Ok(()),
+        };
+        // Reaped either way; e.g. wl-copy outside Wayland exits non-zero, so try the next one
+        match (written, child.wait()) {
+            (Ok(()), Ok(status)) if status.success() => return Ok(()),
+            (Err(e), _) | (_, Err(e)) => last_err = e,
+            _ => last_err = std::io::Error::other(format!("{} failed", command[0])),
+        }
+    }
+    Err(last_err)
+}
+
+#[cfg(feature = "tui")]
+fn open_trash() -> std::io::Result<()> {
+    if cfg!(target_os = "macos") {
+        let trash = dirs::home_dir().unwrap_or_default().join(".Trash");
+        open_in_file_manager(trash.as_os_str())
+    } else if cfg!(target_os = "windows") {
+        open_in_file_manager("shell:RecycleBinFolder".as_ref())
+    } else {
+        open_in_file_manager("trash:///".as_ref())
+    }
+}
+
+/// Every in-flight job keeps `FILES_PER_JOB` descriptors open, so thousands of
+/// targets never mean more than `max_open_files` open at once.
+pub(crate) fn pool_size(max_jobs: Option<usize>, max_open_files: usize) -> usize {
+    let jobs = max_jobs
+        .filter(|n| *n > 0)
+        .unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+    jobs.min(max_open_files / compressor::FILES_PER_JOB).max(1)
+}
+
+/// Roots too broad to crawl without asking: slow, and full of system files
+/// that must never be compressed away.
+const DANGEROUS_ROOTS: &[&str] = &[
+    "/", "/usr", "/etc", "/var", "/bin", "/opt", "/System", "/Library", "/Applications",
+    "C:\\", "C:\\Windows", "C:\\Program Files", "C:\\Users",
+];
+
+/// True for a built-in dangerous root or the user's home directory itself.
+pub fn is_dangerous_root(path: &Path) -> bool {
+    let Ok(path) = path.canonicalize() else { return false };
+    let home = dirs::home_dir().and_then(|h| h.canonicalize().ok());
+    home.as_deref() == Some(path.as_path())
+        || DANGEROUS_ROOTS.iter().any(|root| Path::new(root).canonicalize().is_ok_and(|r| r == path))
+}
+
+/// Explains why `path` can't be scanned, if it can't.
+fn check_scan_path(path: &Path) -> Option<String> {
+    if !path.exists() {
+        Some(format!("Scan path does not exist: {}", path.display()))
+    } else if !path.is_dir() {
+        Some(format!("Scan path is not a directory: {}", path.display()))
+    } else {
+        None
+    }
+}
+
+/// What the app itself uses of ratatui's `TableState` (the cursor), for
+/// builds without the TUI.
+#[cfg(not(feature = "tui"))]
+#[derive(Debug, Default, Clone)]
+pub struct TableState {
+    selected: Option<usize>,
+}
+
+#[cfg(not(feature = "tui"))]
+impl TableState {
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
+    }
+}
+
+// The tests drive the app through its key bindings
+#[cfg(all(test, feature = "tui"))]
+mod tests {
+    use super::*;
+
+    fn dashboard_with_items(n: usize) -> App {
+        let mut app = App::new(PathBuf::from("."), compressor::DEFAULT_LEVEL);
+        app.view = AppView::Dashboard;
+        app.items = (0..n)
+            .map(|i| FileItem::from(ScannedItem {
+                path: PathBuf::from(format!("item{}.log", i)),
+                size: 1024,
+                category: Category::StaleLog,
+                age_days: Some(90),
+                age_basis: None,
+                file_count: None,
+                skip_reason: None,
+            }))
+            .collect();
+        app
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_shift_letter_matches_either_encoding() {
+        let mut app = dashboard_with_items(3);
+
+        // Terminals that report the shifted character
+        app.handle_input(key(KeyCode::Char('A'), KeyModifiers::SHIFT));
+        assert!(app.items.iter().all(|i| i.selected));
+
+        // Terminals that report the base key plus SHIFT
+        app.handle_input(key(KeyCode::Char('a'), KeyModifiers::SHIFT));
+        assert!(app.items.iter().all(|i| !i.selected));
+        assert_eq!(app.sort_order, SortOrder::Size, "Shift-A must not toggle the sort");
+
+        // Plain `a` still sorts
+        app.handle_input(key(KeyCode::Char('a'), KeyModifiers::NONE));
+        assert_eq!(app.sort_order, SortOrder::Age);
+    }
+
+    #[test]
+    fn test_ctrl_combos_do_not_trigger_plain_bindings() {
+        let mut app = dashboard_with_items(2);
+
+        app.handle_input(key(KeyCode::Char(' '), KeyModifiers::CONTROL));
+        app.handle_input(key(KeyCode::Char('a'), KeyModifiers::CONTROL));
+
+        assert!(!app.items[0].selected);
+        assert_eq!(app.sort_order, SortOrder::Size);
+    }
+
+    #[test]
+    fn test_compression_plan_counts_what_c_would_process() {
+        let mut app = dashboard_with_items(3);
+        let (count, size, reclaimable) = app.compression_plan();
+        assert_eq!((count, size), (3, 3 * 1024));
+        assert!(reclaimable > 0 && reclaimable <= size);
+
+        // A selection narrows it down
+        app.items[1].selected = true;
+        assert_eq!(app.compression_plan().0, 1);
+    }
+
+    #[test]
+    fn test_footprint_counts_what_is_on_disk_now() {
+        let mut app = dashboard_with_items(3);
+        assert_eq!(app.current_footprint(), 3 * 1024);
+
+        app.items[0].status = FileStatus::Done;
+        app.items[0].compressed_size = Some(100);
+        app.items[1].status = FileStatus::Deleted;
+        app.items[1].compressed_size = Some(0);
+        assert_eq!(app.current_footprint(), 100 + 1024);
+
+        // An expanded child is already part of its folder
+        app.items[2].path = PathBuf::from("deps");
+        app.items.push(FileItem::from(ScannedItem {
+            path: PathBuf::from("deps/big.bin"), size: 512, category: Category::LargeFile, age_days: None, age_basis: None, file_count: None, skip_reason: None,
+        }));
+        assert_eq!(app.current_footprint(), 100 + 1024);
+    }
+
+    #[test]
+    fn test_broad_scan_roots_need_confirmation() {
+        assert!(is_dangerous_root(Path::new("/")));
+        if let Some(home) = dirs::home_dir() {
+            assert!(is_dangerous_root(&home));
+        }
+        assert!(!is_dangerous_root(Path::new("src")));
+
+        // Scanning `/` asks first; Esc backs out without crawling
+        let mut app = App::new(PathBuf::from("/"), compressor::DEFAULT_LEVEL);
+        app.view = AppView::Dashboard;
+        app.handle_input(key(KeyCode::Char('s'), KeyModifiers::NONE));
+        assert!(app.confirm_broad_scan && !app.is_scanning);
+        app.handle_input(key(KeyCode::Esc, KeyModifiers::NONE));
+        assert!(!app.confirm_broad_scan && !app.is_scanning);
+    }
+
+    #[test]
+    fn test_grouped_rows_follow_project_roots() -> std::io::Result<()> {
+        let root = PathBuf::from("test_group_projects_root");
+        let pkg = root.join("web");
+        std::fs::create_dir_all(pkg.join("node_modules"))?;
+        std::fs::write(pkg.join("package.json"), "{}")?;
+        let found = spyder::project_root(&pkg.join("node_modules"), &root);
+        let outside = spyder::project_root(&root.join("logs/app.log"), &root);
+        std::fs::remove_dir_all(&root)?;
+        assert_eq!(found, Some(pkg.clone()));
+        assert_eq!(outside, None);
+
+        let mut app = dashboard_with_items(3);
+        app.items[0].project = Some(pkg.clone());
+        app.items[2].project = Some(pkg.clone());
+        app.handle_input(key(KeyCode::Char('g'), KeyModifiers::NONE));
+        assert_eq!(app.rows(), vec![
+            ListRow::Project(Some(pkg.clone()), 2, 2048, false),
+            ListRow::Item(0),
+            ListRow::Item(2),
+            ListRow::Project(None, 1, 1024, false),
+            ListRow::Item(1),
+        ]);
+
+        // Space on a header selects the project; Enter folds it
+        app.list_state.select(Some(0));
+        app.handle_input(key(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert!(app.items[0].selected && app.items[2].selected && !app.items[1].selected);
+        app.handle_input(key(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!app.show_details);
+        assert_eq!(app.rows().len(), 3);
+        app.handle_input(key(KeyCode::Down, KeyModifiers::NONE));
+        assert_eq!(app.selected_item(), None, "The other section's header");
+        Ok(())
+    }
+
+    /// Drives `tick` until the background scan is done.
+    fn finish_scan(app: &mut App) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while app.is_scanning && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+            app.tick();
+        }
+    }
+
+    #[test]
+    fn test_refresh_keeps_selection_by_path() -> std::io::Result<()> {
+        let root = PathBuf::from("test_refresh_root");
+        std::fs::create_dir_all(&root)?;
+        for name in ["a.bin", "b.bin", "c.bin"] {
+            std::fs::write(root.join(name), vec![1u8; 64])?;
+        }
+        let mut app = App::new(root.clone(), compressor::DEFAULT_LEVEL);
+        app.view = AppView::Dashboard;
+        app.big_file_threshold = Some(1);
+
+        app.handle_input(key(KeyCode::Char('s'), KeyModifiers::NONE));
+        finish_scan(&mut app);
+        let b = app.items.iter().position(|i| i.path.ends_with("b.bin")).expect("b.bin listed");
+        app.items[b].selected = true;
+        app.select_item(b);
+        std::fs::remove_file(root.join("a.bin"))?;
+
+        app.handle_input(key(KeyCode::Char('r'), KeyModifiers::NONE));
+        assert!(app.refresh.is_some());
+        finish_scan(&mut app);
+        std::fs::remove_dir_all(&root)?;
+
+        assert_eq!(app.items.len(), 2);
+        let selected: Vec<_> = app.items.iter().filter(|i| i.selected).map(|i| i.path.clone()).collect();
+        assert_eq!(selected.len(), 1);
+        assert!(selected[0].ends_with("b.bin"));
+        assert!(app.selected_item().is_some_and(|i| app.items[i].path.ends_with("b.bin")));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths_survive_the_scan() -> std::io::Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = PathBuf::from("test_non_utf8_root");
+        std::fs::create_dir_all(&root)?;
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.bin");
+        std::fs::write(root.join(name), vec![1u8; 64])?;
+
+        let mut app = App::new(root.clone(), compressor::DEFAULT_LEVEL);
+        app.view = AppView::Dashboard;
+        app.big_file_threshold = Some(1);
+        app.handle_input(key(KeyCode::Char('s'), KeyModifiers::NONE));
+        finish_scan(&mut app);
+        let found = app.items.iter().map(|i| i.path.clone()).collect::<Vec<_>>();
+        std::fs::remove_dir_all(&root)?;
+
+        assert_eq!(found, vec![root.join(name)], "Lossy conversion would point elsewhere");
+        Ok(())
+    }
+
+    #[test]
+    fn test_failures_keep_their_error_message() {
+        let mut app = dashboard_with_items(2);
+        let tx = app.worker.job();
+        app.is_compressing = true;
+
+        tx.send(AppMessage::CompressionProgress(0, Err("Permission denied (os error 13)".to_string())));
+        let skipped = CompressionStats {
+            original_size: 1024,
+            compressed_size: 1024,
+            output_path: PathBuf::from("item1.log"),
+            skipped: Some("Already compressed".to_string()),
+            retries: 0,
+        };
+        tx.send(AppMessage::CompressionProgress(1, Ok(skipped)));
+        app.tick();
+
+        assert_eq!(app.problems.len(), 2);
+        assert_eq!(app.problems[0].severity, Severity::Error);
+        assert_eq!(app.problems[0].path, PathBuf::from("item0.log"));
+        assert_eq!(app.problems[0].message.as_deref(), Some("Permission denied (os error 13)"));
+        assert_eq!(app.items[0].reason, "Permission denied (os error 13)");
+        assert_eq!(app.problems[1].severity, Severity::Warning);
+        assert_eq!(app.problems[1].reason, "Already compressed");
+    }
+
+    #[test]
+    fn test_overlapping_jobs_are_all_applied() {
+        let mut app = dashboard_with_items(0);
+        let mut found = dashboard_with_items(1).items;
+        found[0].status = FileStatus::Done;
+        found[0].compressed_size = Some(100);
+
+        // A refresh lands, then a restore that was running alongside it
+        // reports; neither flag is set any more by the time `tick` runs
+        let (scan, restore) = (app.worker.job(), app.worker.job());
+        thread::spawn(move || scan.send(AppMessage::ScanComplete(found, Vec::new(), 1))).join().unwrap();
+        thread::spawn(move || restore.send(AppMessage::RestorationDone(0, Ok(())))).join().unwrap();
+        app.tick();
+
+        assert!(app.has_scanned);
+        assert_eq!(app.items.len(), 1);
+        assert!(app.items[0].status == FileStatus::Restored);
+        assert_eq!(app.items[0].compressed_size, None);
+    }
+
+    #[test]
+    fn test_compression_summary_counts_the_batch() {
+        let mut app = dashboard_with_items(4);
+        app.list_state.select(Some(0));
+        app.is_compressing = true;
+        app.compress_batch = vec![0, 1, 2];
+        app.compress_started = Some(Instant::now());
+        app.batch_savings = 900;
+        app.items[0].status = FileStatus::Done;
+        app.items[1].status = FileStatus::Skipped;
+        app.items[2].status = FileStatus::Error;
+        app.items[3].status = FileStatus::Skipped; // Not part of the batch
+
+        app.worker.job().send(AppMessage::CompressionDone);
+        app.tick();
+        let summary = app.compression_summary.as_ref().expect("summary shown");
+        assert_eq!((summary.done, summary.skipped, summary.errors, summary.saved), (1, 1, 1, 900));
+
+        // Any key closes it, and does nothing else
+        app.handle_input(key(KeyCode::Char('j'), KeyModifiers::NONE));
+        assert!(app.compression_summary.is_none());
+        assert_eq!(app.list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn test_select_largest_ignores_sort_and_done_items() {
+        let mut app = dashboard_with_items(5);
+        for (item, size) in app.items.iter_mut().zip([10, 50, 30, 40, 20]) {
+            item.original_size = size;
+        }
+        app.items[1].status = FileStatus::Done;
+        app.items[0].selected = true;
+
+        assert_eq!(app.select_largest(2), 2);
+        let selected: Vec<usize> = (0..5).filter(|&i| app.items[i].selected).collect();
+        assert_eq!(selected, vec![2, 3]);
+        assert_eq!(app.select_largest(10), 4);
+    }
+
+    #[test]
+    fn test_details_close_when_their_item_goes_away() {
+        let mut app = dashboard_with_items(0);
+        app.handle_input(key(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!app.show_details, "Nothing to show on an empty list");
+
+        app.items = dashboard_with_items(3).items;
+        app.list_state.select(Some(2));
+        app.handle_input(key(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.show_details);
+
+        // e.g. a rescan that found fewer items
+        app.items.truncate(1);
+        app.handle_input(key(KeyCode::Char('z'), KeyModifiers::NONE));
+        assert_eq!(app.list_state.selected(), Some(0));
+        assert_eq!(app.selected_item(), Some(0));
+
+        app.items.clear();
+        app.handle_input(key(KeyCode::Char('z'), KeyModifiers::NONE));
+        assert!(!app.show_details);
+    }
+
+    #[test]
+    fn test_hide_completed_remaps_the_cursor() {
+        let mut app = dashboard_with_items(4);
+        app.items[0].status = FileStatus::Done;
+        app.items[2].status = FileStatus::Deleted;
+        app.list_state.select(Some(3));
+
+        app.handle_input(key(KeyCode::Char('h'), KeyModifiers::NONE));
+        assert_eq!(app.rows(), vec![ListRow::Item(1), ListRow::Item(3)]);
+        assert_eq!(app.selected_item(), Some(3), "Cursor follows its item to the new row");
+
+        app.handle_input(key(KeyCode::Char('h'), KeyModifiers::NONE));
+        assert_eq!(app.rows().len(), 4);
+        assert_eq!(app.selected_item(), Some(3));
+
+        // Hiding the item under the cursor leaves it on a neighbour
+        app.list_state.select(Some(2));
+        app.handle_input(key(KeyCode::Char('h'), KeyModifiers::NONE));
+        assert_eq!(app.selected_item(), Some(3));
+    }
+
+    #[test]
+    fn test_status_message_expires_on_tick() {
+        let mut app = dashboard_with_items(2);
+        app.handle_input(key(KeyCode::Char('h'), KeyModifiers::NONE));
+        app.tick();
+        assert!(app.status_message.is_some());
+
+        let shown = Instant::now().checked_sub(STATUS_MESSAGE_TTL).unwrap();
+        app.status_message = Some(("Scan complete: 2 candidates".to_string(), shown));
+        app.tick();
+        assert!(app.status_message.is_none());
+    }
+
+    #[test]
+    fn test_resize_picks_up_new_contents() -> std::io::Result<()> {
+        let dir = PathBuf::from("test_resize_item");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("a.o"), vec![1u8; 100])?;
+        let mut app = dashboard_with_items(1);
+        app.items[0].path = dir.clone();
+        app.items[0].file_count = Some(1);
+        app.list_state.select(Some(0));
+
+        // A build added to it since the scan
+        std::fs::write(dir.join("b.o"), vec![1u8; 300])?;
+        app.handle_input(key(KeyCode::Char('f'), KeyModifiers::NONE));
+        assert!(app.resizing.is_some());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while app.resizing.is_some() && Instant::now() < deadline {
+            app.tick();
+            thread::sleep(Duration::from_millis(10));
+        }
+        std::fs::remove_dir_all(&dir)?;
+
+        assert_eq!((app.items[0].original_size, app.items[0].file_count), (400, Some(2)));
+        assert!(!app.is_scanning);
+        Ok(())
+    }
+
+    #[test]
+    fn test_permanent_delete_needs_shift_y() -> std::io::Result<()> {
+        let dir = PathBuf::from("test_purge_item");
+        std::fs::create_dir_all(dir.join("nested"))?;
+        std::fs::write(dir.join("nested/a.o"), vec![1u8; 100])?;
+        let mut app = dashboard_with_items(1);
+        app.items[0].path = dir.clone();
+        app.list_state.select(Some(0));
+
+        // A plain `y` backs out
+        app.handle_input(key(KeyCode::Char('D'), KeyModifiers::SHIFT));
+        assert!(app.pending_purge.is_some());
+        app.handle_input(key(KeyCode::Char('y'), KeyModifiers::NONE));
+        let kept = dir.exists();
+
+        app.handle_input(key(KeyCode::Char('d'), KeyModifiers::SHIFT));
+        app.handle_input(key(KeyCode::Char('y'), KeyModifiers::SHIFT));
+        let removed = !dir.exists();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(kept && removed);
+        assert!(app.pending_purge.is_none());
+        assert!(app.items[0].status == FileStatus::Deleted);
+        assert_eq!(app.total_savings, 1024);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shutdown_waits_for_items_in_hand() -> std::io::Result<()> {
+        let dir = PathBuf::from("test_shutdown_dir");
+        std::fs::create_dir_all(&dir)?;
+        let mut app = dashboard_with_items(6);
+        for (i, item) in app.items.iter_mut().enumerate() {
+            item.path = dir.join(format!("{}.log", i));
+            std::fs::write(&item.path, "line\n".repeat(50_000))?;
+        }
+
+        app.start_compression();
+        let finished = app.shutdown(Duration::from_secs(10));
+        // Every item is either fully compressed or untouched, never in between
+        let consistent = app.items.iter().all(|item| {
+            let artifact = compressor::output_path_for(&item.path, false, &app.compress_options());
+            match item.status {
+                FileStatus::Done => !item.path.exists() && artifact.exists(),
+                _ => item.path.exists() && !artifact.exists(),
+            }
+        });
+        std::fs::remove_dir_all(&dir)?;
+
+        assert!(finished && !app.has_running_workers());
+        assert!(consistent);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shift_tab_switches_tabs() {
+        let mut app = dashboard_with_items(0);
+
+        app.handle_input(key(KeyCode::BackTab, KeyModifiers::SHIFT));
+        assert_eq!(app.current_tab, AppTab::Problems);
+
+        app.handle_input(key(KeyCode::BackTab, KeyModifiers::SHIFT));
+        assert_eq!(app.current_tab, AppTab::Status);
+
+        app.handle_input(key(KeyCode::Tab, KeyModifiers::NONE));
+        assert_eq!(app.current_tab, AppTab::Problems);
     }
 }
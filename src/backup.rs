@@ -0,0 +1,186 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use anyhow::{Result, Context};
+use chrono::{Local, NaiveDateTime, TimeZone};
+
+/// Name format of the per-session directories under a store.
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Overrides `data_dir`, e.g. to keep Piper's data on a synced drive.
+pub const DATA_DIR_ENV: &str = "PIPER_DATA_DIR";
+
+/// Piper's own data directory: `$PIPER_DATA_DIR` if set, else the platform's
+/// (`$XDG_DATA_HOME/piper`, i.e. `~/.local/share/piper` on Linux,
+/// `~/Library/Application Support/piper` on macOS, `%APPDATA%\piper` on Windows).
+/// A `~/.piper` that couldn't be migrated keeps being used.
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    match (platform_data_dir(), legacy_data_dir()) {
+        (Some(dir), _) if dir.exists() => Some(dir),
+        (_, Some(legacy)) if legacy.exists() => Some(legacy),
+        (dir, _) => dir,
+    }
+}
+
+fn platform_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("piper"))
+}
+
+/// Where everything lived before Piper followed platform conventions.
+fn legacy_data_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".piper"))
+}
+
+/// Moves `~/.piper` to the platform data directory, once. Returns the new
+/// location if something was moved.
+pub fn migrate_legacy_data_dir() -> Result<Option<PathBuf>> {
+    if std::env::var_os(DATA_DIR_ENV).is_some_and(|dir| !dir.is_empty()) {
+        return Ok(None);
+    }
+    match (legacy_data_dir(), platform_data_dir()) {
+        (Some(from), Some(to)) => migrate_dir(&from, &to).map(|moved| moved.then_some(to)),
+        _ => Ok(None),
+    }
+}
+
+/// Renames `from` to `to` unless `from` is missing or `to` already exists.
+fn migrate_dir(from: &Path, to: &Path) -> Result<bool> {
+    if !from.is_dir() || to.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::rename(from, to)
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+    Ok(true)
+}
+
+/// Where deletes land when the OS trash isn't available.
+pub fn trash_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("trash"))
+}
+
+/// Where replaced or deleted originals go when `[backup] enabled = true`.
+pub fn backups_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("backups"))
+}
+
+/// A fresh `<store>/<timestamp>` directory name for one session's moves.
+pub fn session_dir(store: &Path) -> PathBuf {
+    store.join(Local::now().format(STAMP_FORMAT).to_string())
+}
+
+/// Moves `path` to `<dir>/<its absolute path>`, so it can be found (and put
+/// back by hand) later. Returns the new location.
+pub fn move_into(dir: &Path, path: &Path) -> Result<PathBuf> {
+    let source = std::path::absolute(path)?;
+
+    // Drop the root/prefix so the absolute path nests under `dir`
+    let relative: PathBuf = source.components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect();
+    let target = dir.join(relative);
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    // A plain rename: it can't cross filesystems, but it never leaves a half-copied tree
+    fs::rename(&source, &target)
+        .with_context(|| format!("Failed to move into {}", dir.display()))?;
+    Ok(target)
+}
+
+/// One session directory in a store.
+#[derive(Debug, Clone)]
+pub struct BackupSession {
+    pub path: PathBuf,
+    pub created: SystemTime,
+    pub size: u64,
+}
+
+/// Sessions in `store`, oldest first. A missing store is just empty.
+pub fn list(store: &Path) -> Result<Vec<BackupSession>> {
+    let entries = match fs::read_dir(store) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", store.display())),
+    };
+
+    let mut sessions: Vec<BackupSession> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| {
+            let created = session_time(&e.file_name().to_string_lossy())?;
+            Some(BackupSession { size: dir_size(&e.path()), path: e.path(), created })
+        })
+        .collect();
+    sessions.sort_by_key(|s| s.created);
+    Ok(sessions)
+}
+
+/// Deletes sessions older than `retention`. Returns how many were removed.
+pub fn prune(store: &Path, retention: Duration) -> Result<usize> {
+    let cutoff = SystemTime::now().checked_sub(retention).unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut removed = 0;
+    for session in list(store)?.into_iter().filter(|s| s.created < cutoff) {
+        fs::remove_dir_all(&session.path)
+            .with_context(|| format!("Failed to remove {}", session.path.display()))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Permanently deletes every session in `store`. Returns the bytes freed.
+pub fn empty(store: &Path) -> Result<u64> {
+    let mut freed = 0;
+    for session in list(store)? {
+        fs::remove_dir_all(&session.path)
+            .with_context(|| format!("Failed to remove {}", session.path.display()))?;
+        freed += session.size;
+    }
+    Ok(freed)
+}
+
+fn session_time(name: &str) -> Option<SystemTime> {
+    let naive = NaiveDateTime::parse_from_str(name, STAMP_FORMAT).ok()?;
+    let local = Local.from_local_datetime(&naive).earliest()?;
+    Some(local.into())
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_dir_moves_once() -> Result<()> {
+        let from = PathBuf::from("test_migrate_legacy_piper");
+        let to = PathBuf::from("test_migrate_xdg").join("piper");
+        fs::create_dir_all(from.join("backups"))?;
+        fs::write(from.join("history.json"), "{}")?;
+
+        let moved = migrate_dir(&from, &to)?;
+        let again = migrate_dir(&from, &to)?;
+        let history = to.join("history.json").exists();
+        fs::remove_dir_all("test_migrate_xdg")?;
+
+        assert!(moved && !again);
+        assert!(history && !from.exists());
+        Ok(())
+    }
+}
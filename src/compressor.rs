@@ -1,119 +1,936 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use anyhow::Result;
 
+use crate::backup;
+use crate::naming::Naming;
+use crate::persist;
+use crate::sidecar::Sidecar;
+use crate::spyder;
+
+/// Range of zstd levels Piper exposes (zstd's negative "fast" levels are left out).
+pub const MIN_LEVEL: i32 = 1;
+pub const MAX_LEVEL: i32 = 22;
+/// Pied Piper "Middle-Out" Level
+pub const DEFAULT_LEVEL: i32 = 15;
+
+/// Knobs for a single `compress_with` call.
+#[derive(Debug, Clone)]
+pub struct CompressOptions {
+    pub level: i32,
+    /// Encoder settings artifacts are written with; see `Backend`.
+    pub backend: Backend,
+    /// Ceiling on whatever `level_for` picks; see `memory::fit`.
+    pub max_level: i32,
+    /// Leave the source in place next to the artifact instead of replacing it.
+    pub keep_original: bool,
+    /// Write artifacts under this directory instead of next to the source,
+    /// mirroring the source's path relative to `root`.
+    pub output_dir: Option<PathBuf>,
+    /// Scan root that `output_dir` paths are relative to.
+    pub root: Option<PathBuf>,
+    /// Move replaced originals here (see `backup::session_dir`) instead of deleting them.
+    pub backup_dir: Option<PathBuf>,
+    /// Tune `level` per file type; see `level_for`.
+    pub adaptive_levels: bool,
+    /// Fixed levels by file extension (e.g. `"log" => 19`), applied even when
+    /// `adaptive_levels` is off.
+    pub level_overrides: HashMap<String, i32>,
+    /// Archive what symlinks inside a directory point to instead of the links
+    /// themselves, so a restore doesn't depend on the targets. Can make the
+    /// archive much larger than the folder looks; cycles are refused.
+    pub dereference_symlinks: bool,
+    /// Never compressed, nor anything inside them; see `protected_reason`.
+    /// Defaults to `PROTECTED_PATHS`.
+    pub protected_paths: Vec<PathBuf>,
+    /// Decode each new artifact and compare checksums before the original
+    /// is removed; see `Stage`. Costs a second pass over the artifact.
+    pub verify: bool,
+    /// How artifacts are named; see `naming::Naming`.
+    pub naming: Naming,
+    /// Refuse inputs with anything modified this recently, e.g. a build
+    /// still writing to `target/`; see `recently_modified`. Off by default.
+    pub protect_recent: Option<Duration>,
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        CompressOptions {
+            level: DEFAULT_LEVEL,
+            backend: Backend::Zstd,
+            max_level: MAX_LEVEL,
+            keep_original: false,
+            output_dir: None,
+            root: None,
+            backup_dir: None,
+            adaptive_levels: false,
+            level_overrides: HashMap::new(),
+            dereference_symlinks: false,
+            protected_paths: default_protected_paths(),
+            verify: false,
+            naming: Naming::default(),
+            protect_recent: None,
+        }
+    }
+}
+
+/// Where the artifact for `input_path` goes: named by `opts.naming`
+/// (`<name>.tar.zst` for directories, `<name>.<ext>.zst` for files by
+/// default), either next to the source or under `output_dir`.
+pub fn output_path_for(input_path: &Path, is_dir: bool, opts: &CompressOptions) -> PathBuf {
+    let base = match &opts.output_dir {
+        Some(dest) => {
+            let relative = opts.root.as_ref()
+                .and_then(|root| input_path.strip_prefix(root).ok())
+                .filter(|rel| !rel.as_os_str().is_empty())
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from(input_path.file_name().unwrap_or_default()));
+            dest.join(relative)
+        }
+        None => input_path.to_path_buf(),
+    };
+
+    let name = opts.naming.artifact_name(base.file_name().unwrap_or(base.as_os_str()), is_dir);
+    base.with_file_name(name)
+}
+
+/// The existing artifact for a source compressed with `opts`, preferring the
+/// directory form since the source itself may be gone.
+pub fn find_artifact(input_path: &Path, opts: &CompressOptions) -> PathBuf {
+    let tar_path = output_path_for(input_path, true, opts);
+    if tar_path.exists() {
+        tar_path
+    } else {
+        output_path_for(input_path, false, opts)
+    }
+}
+
 pub struct CompressionStats {
     pub original_size: u64,
     pub compressed_size: u64,
     pub output_path: PathBuf,
+    pub skipped: Option<String>, // Why nothing was written, e.g. "Already compressed"
+    pub retries: u32, // Transient I/O errors that were retried successfully
+}
+
+impl CompressionStats {
+    /// Input left untouched on purpose (not a failure).
+    fn skipped(input_path: &Path, original_size: u64, reason: &str) -> Self {
+        CompressionStats {
+            original_size,
+            compressed_size: original_size,
+            output_path: input_path.to_path_buf(),
+            skipped: Some(reason.to_string()),
+            retries: 0,
+        }
+    }
+}
+
+/// Text compresses so well that a few extra levels are cheap and pay off.
+const TEXT_EXTENSIONS: &[&str] = &["log", "txt", "old", "csv", "tsv", "json", "jsonl", "xml", "md", "sql", "html"];
+/// Already-compressed formats barely shrink; a fast level saves the CPU.
+const DENSE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "mp3", "mp4", "mkv", "mov", "zip", "gz", "xz", "bz2", "7z", "jar", "whl"];
+const TEXT_LEVEL_BUMP: i32 = 4;
+const TEXT_LEVEL_CAP: i32 = 19; // Above this zstd needs far more memory for little gain
+const DENSE_LEVEL: i32 = 3;
+
+/// The level actually used for `input_path`:
+///
+/// 1. an entry in `level_overrides` for its extension, else
+/// 2. with `adaptive_levels`: text files get `level + 4` (capped at 19), dense
+///    media/archives get at most 3, and everything else (including directories)
+///    keeps `level`.
+pub fn level_for(input_path: &Path, opts: &CompressOptions) -> i32 {
+    let ext = input_path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let level = if let Some(level) = opts.level_overrides.get(&ext) {
+        *level
+    } else if !opts.adaptive_levels || input_path.is_dir() {
+        opts.level
+    } else if TEXT_EXTENSIONS.contains(&ext.as_str()) {
+        (opts.level + TEXT_LEVEL_BUMP).min(TEXT_LEVEL_CAP.max(opts.level))
+    } else if DENSE_EXTENSIONS.contains(&ext.as_str()) {
+        opts.level.min(DENSE_LEVEL)
+    } else {
+        opts.level
+    };
+    level.clamp(MIN_LEVEL, opts.max_level.clamp(MIN_LEVEL, MAX_LEVEL))
+}
+
+/// The highest level `level_for` can pick under `opts`, whatever the file.
+pub fn peak_level(opts: &CompressOptions) -> i32 {
+    let adaptive = if opts.adaptive_levels {
+        (opts.level + TEXT_LEVEL_BUMP).min(TEXT_LEVEL_CAP.max(opts.level))
+    } else {
+        opts.level
+    };
+    opts.level_overrides.values().copied()
+        .fold(adaptive, i32::max)
+        .clamp(MIN_LEVEL, opts.max_level.clamp(MIN_LEVEL, MAX_LEVEL))
+}
+
+/// Compression engines Piper can drive. Every one writes standard zstd frames,
+/// so any zstd decoder restores them. (gzip isn't linked in; zstd beats it at
+/// every level, so it wouldn't win a comparison anyway.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Zstd,
+    /// zstd with long-distance matching over a 128 MiB window: pays off on big
+    /// trees that repeat content far apart, like `node_modules`.
+    ZstdLong,
 }
 
+impl Backend {
+    pub const ALL: [Backend; 2] = [Backend::Zstd, Backend::ZstdLong];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Zstd => "zstd",
+            Backend::ZstdLong => "zstd-long",
+        }
+    }
+
+    /// A zstd encoder at `level` configured for this backend.
+    fn encoder<W: Write>(&self, writer: W, level: i32) -> std::io::Result<zstd::stream::write::Encoder<'static, W>> {
+        let mut encoder = zstd::stream::write::Encoder::new(writer, level)?;
+        if *self == Backend::ZstdLong {
+            encoder.long_distance_matching(true)?;
+            encoder.window_log(LONG_WINDOW_LOG)?;
+        }
+        Ok(encoder)
+    }
+
+    /// Compressed size of `data` at `level`, without writing anything.
+    fn compressed_len(&self, data: &[u8], level: i32) -> Result<u64> {
+        let mut encoder = self.encoder(Vec::new(), level)?;
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?.len() as u64)
+    }
+}
+
+/// 128 MiB, the largest window decoders accept without extra flags.
+const LONG_WINDOW_LOG: u32 = 27;
+
+/// How much of an input `estimate_best_backend` compresses; enough for a
+/// stable ratio while keeping the details popup responsive.
+pub const SAMPLE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Compresses a sample (the first `SAMPLE_BYTES` of the file, or of a
+/// directory's files in walk order) with every `Backend` and returns the one
+/// with the smallest output. Nothing is written to disk; the stats describe
+/// the sample, not the whole input.
+pub fn estimate_best_backend(input_path: &Path, opts: &CompressOptions) -> Result<(Backend, CompressionStats)> {
+    let sample = read_sample(input_path)?;
+    if sample.is_empty() {
+        return Err(anyhow::anyhow!(EMPTY_REASON));
+    }
+
+    let level = level_for(input_path, opts);
+    let mut best: Option<(Backend, u64)> = None;
+    for backend in Backend::ALL {
+        let size = backend.compressed_len(&sample, level)?;
+        if best.is_none_or(|(_, best_size)| size < best_size) {
+            best = Some((backend, size));
+        }
+    }
+
+    let (backend, compressed_size) = best.ok_or(anyhow::anyhow!("No backends available"))?;
+    Ok((backend, CompressionStats {
+        original_size: sample.len() as u64,
+        compressed_size,
+        output_path: input_path.to_path_buf(),
+        skipped: None,
+        retries: 0,
+    }))
+}
+
+fn read_sample(input_path: &Path) -> Result<Vec<u8>> {
+    let mut sample = Vec::new();
+    let files = walkdir::WalkDir::new(input_path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in files {
+        let remaining = SAMPLE_BYTES - sample.len() as u64;
+        if remaining == 0 {
+            break;
+        }
+        // Unreadable files are skipped; the sample only needs to be representative
+        if let Ok(file) = File::open(entry.path()) {
+            let _ = file.take(remaining).read_to_end(&mut sample);
+        }
+    }
+    Ok(sample)
+}
+
+/// Compresses and replaces `input_path` at `level`. See `compress_with` for more control.
 pub fn compress_file(input_path: &Path, level: i32) -> Result<CompressionStats> {
-    let metadata = input_path.metadata()?;
+    compress_with(input_path, &CompressOptions { level, ..Default::default() })
+}
+
+/// Reason reported when the input was removed by someone else after the scan.
+pub const VANISHED_REASON: &str = "File no longer exists";
+
+/// Reason reported for zero-byte files and directories holding no data.
+pub const EMPTY_REASON: &str = "Empty, nothing to compress";
+
+/// Reason reported for inputs changed within `protect_recent`.
+pub const RECENT_REASON: &str = "Recently modified, skipped";
+
+/// Reason reported for files mostly made of holes; see `spyder::is_sparse`.
+pub const SPARSE_REASON: &str = "Sparse file, minimal real size";
+
+/// The `protect_recent_minutes` the config falls back to.
+pub const DEFAULT_PROTECT_RECENT_MINUTES: u64 = 5;
+
+/// Whether `path`, or anything inside it, was modified within `window`.
+/// Walks the whole tree for directories.
+pub fn recently_modified(path: &Path, window: Duration) -> bool {
+    let modified = match path.metadata() {
+        Ok(m) if m.is_dir() => spyder::dir_stats(path).modified,
+        Ok(m) => m.modified().ok(),
+        Err(_) => None,
+    };
+    spyder::modified_within(modified, window)
+}
+
+/// Built-in paths that are never compressed, whatever the scan turns up:
+/// compression removes originals, and losing keys or system files to a bad
+/// config would be far worse than a missed saving. `~` is the home directory.
+pub const PROTECTED_PATHS: &[&str] = &[
+    "~/.ssh", "~/.gnupg", "~/.config", "~/.aws", "~/.kube",
+    "/System", "/usr", "/etc", "/bin", "/sbin", "/boot", "C:\\Windows",
+];
+
+/// `PROTECTED_PATHS` with `~` expanded.
+pub fn default_protected_paths() -> Vec<PathBuf> {
+    PROTECTED_PATHS.iter().map(|p| expand_home(p)).collect()
+}
+
+/// Replaces a leading `~` with the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home.join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Why `path` must not be compressed: it is the home directory itself, or
+/// one of `protected` or inside it. Symlinks are resolved on both sides.
+pub fn protected_reason(path: &Path, protected: &[PathBuf]) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    if dirs::home_dir().and_then(|h| h.canonicalize().ok()).as_deref() == Some(path.as_path()) {
+        return Some("Protected: home directory".to_string());
+    }
+    protected.iter()
+        .find(|p| path.starts_with(p.canonicalize().unwrap_or_else(|_| p.to_path_buf())))
+        .map(|p| format!("Protected path: {}", p.display()))
+}
+
+/// Descriptors one compress job holds at once: the source and the temp output.
+/// Directories are streamed into the tar one file at a time, so they cost the same.
+pub const FILES_PER_JOB: usize = 2;
+
+/// What a `Progress` callback hears about while an input is being compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressUpdate {
+    /// Running total of bytes fed to the encoder. For directories this counts
+    /// the tar stream, so it ends slightly above the summed file sizes.
+    Bytes(u64),
+    /// Directories only: entries appended to the tar so far, out of `total`.
+    Files { added: u64, total: u64 },
+}
+
+pub type Progress<'a> = &'a (dyn Fn(ProgressUpdate) + Sync);
+
+pub fn compress_with(input_path: &Path, opts: &CompressOptions) -> Result<CompressionStats> {
+    compress_with_progress(input_path, opts, None)
+}
+
+/// `compress_with`, reporting progress as the input is consumed.
+pub fn compress_with_progress(input_path: &Path, opts: &CompressOptions, progress: Option<Progress>) -> Result<CompressionStats> {
+    compress_path(input_path, opts, progress).map_err(explain_fd_exhaustion)
+}
+
+fn compress_path(input_path: &Path, opts: &CompressOptions, progress: Option<Progress>) -> Result<CompressionStats> {
+    if let Some(reason) = protected_reason(input_path, &opts.protected_paths) {
+        return Ok(CompressionStats::skipped(input_path, 0, &reason));
+    }
+
+    let metadata = match input_path.metadata() {
+        Ok(m) => m,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(CompressionStats::skipped(input_path, 0, VANISHED_REASON));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if let Some(window) = opts.protect_recent
+        && recently_modified(input_path, window)
+    {
+        return Ok(CompressionStats::skipped(input_path, 0, RECENT_REASON));
+    }
     
     if metadata.is_dir() {
-        compress_directory(input_path, level)
+        compress_directory(input_path, opts, progress)
+    } else if metadata.len() == 0 {
+        // Any archive would be all header, larger than the source
+        Ok(CompressionStats::skipped(input_path, 0, EMPTY_REASON))
+    } else if spyder::is_sparse(&metadata) {
+        // The archive would hold the holes as data: a long read for little
+        // saving, and restoring it could take more disk than the original
+        Ok(CompressionStats::skipped(input_path, 0, SPARSE_REASON))
+    } else {
+        compress_single_file(input_path, opts, metadata.len(), progress)
+    }
+}
+
+/// The raw "Too many open files (os error 24)" gives no hint how to fix it.
+fn explain_fd_exhaustion(err: anyhow::Error) -> anyhow::Error {
+    if is_fd_exhaustion(&err) {
+        err.context("Too many open files; lower max_jobs or max_open_files in the config")
     } else {
-        compress_single_file(input_path, level, metadata.len())
+        err
     }
 }
 
-fn compress_single_file(input_path: &Path, level: i32, original_size: u64) -> Result<CompressionStats> {
-    let input_file = File::open(input_path)?;
-    let reader = BufReader::new(input_file);
+fn is_fd_exhaustion(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .filter_map(|io| io.raw_os_error())
+        .any(|code| {
+            if cfg!(windows) {
+                code == 4 // ERROR_TOO_MANY_OPEN_FILES
+            } else {
+                code == 23 || code == 24 // ENFILE, EMFILE
+            }
+        })
+}
+
+/// Another process has the file open in a way that blocks removing it:
+/// a sharing or lock violation on Windows, busy text or device on Unix.
+fn is_sharing_violation(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .filter_map(|io| io.raw_os_error())
+        .any(|code| {
+            if cfg!(windows) {
+                code == 32 || code == 33 // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+            } else {
+                code == 16 || code == 26 // EBUSY, ETXTBSY
+            }
+        })
+}
+
+/// Why a compression was rolled back instead of leaving two copies.
+const LOCKED_REASON: &str = "Original is open in another program; artifact removed, original left in place";
+
+fn compress_single_file(input_path: &Path, opts: &CompressOptions, original_size: u64, progress: Option<Progress>) -> Result<CompressionStats> {
+    compress_file_as(input_path, input_path, opts, original_size, progress)
+}
+
+/// Compresses the file `source` into the artifact for `input_path`, which is
+/// either the file itself or a folder holding nothing else.
+fn compress_file_as(source: &Path, input_path: &Path, opts: &CompressOptions, original_size: u64, progress: Option<Progress>) -> Result<CompressionStats> {
+    // Still racy after the metadata check: another process may delete it in between
+    let mut retries = 0;
+    let input_file = match with_retry(&mut retries, || File::open(source)) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(CompressionStats::skipped(input_path, original_size, VANISHED_REASON));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut input = Retrying { inner: input_file, retries };
+    let reader = Counting::new(BufReader::new(&mut input), progress);
+    let original_mtime = modified_time(input_path);
 
     // Atomic Write Pattern: Write to .tmp first
-    let output_path = input_path.with_extension(format!("{}.zst", input_path.extension().unwrap_or_default().to_string_lossy()));
+    let output_path = output_path_for(input_path, false, opts);
+    check_output_free(&output_path)?;
+    if output_path.exists() {
+        // Re-runs (e.g. nightly cron) must not clobber an earlier artifact
+        return Ok(CompressionStats::skipped(input_path, original_size, "Already compressed"));
+    }
     let temp_path = output_path.with_extension("zst.tmp");
+    prepare_output_dir(&output_path, opts)?;
     
-    let output_file = File::create(&temp_path)?;
-    let writer = BufWriter::new(output_file);
-
     // Pied Piper "Middle-Out" Level (Configurable)
-    match zstd::stream::copy_encode(reader, writer, level) {
-        Ok(_) => {},
-        Err(e) => {
-            let _ = std::fs::remove_file(&temp_path);
-            return Err(e.into());
+    let checksum = run_stage(Stage::Compress, &temp_path, || {
+        let writer = BufWriter::new(File::create(&temp_path)?);
+        encode_stream(reader, writer, level_for(source, opts), opts)
+    })?;
+
+    let mut stats = finalize_compression(input_path, &output_path, &temp_path, original_size, checksum, opts)?;
+    stats.retries = input.retries;
+    let inner_path = (source != input_path).then(|| PathBuf::from(source.file_name().unwrap_or_default()));
+    record_origin(input_path, &stats, inner_path, original_mtime)?;
+    Ok(stats)
+}
+
+/// Attempts per operation before a transient error is given up on.
+const MAX_ATTEMPTS: u32 = 3;
+/// Wait before the first retry; doubled for each one after.
+const RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Errors a network filesystem (SMB/NFS) may return once and then recover
+/// from. Anything else, like PermissionDenied or NotFound, fails at once.
+fn is_transient(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(err.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Runs `op` until it succeeds, fails permanently, or runs out of attempts,
+/// counting each retry in `retries`.
+fn with_retry<T>(retries: &mut u32, mut op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => {
+                std::thread::sleep(RETRY_BACKOFF * 2u32.pow(attempt - 1));
+                attempt += 1;
+                *retries += 1;
+            }
+            result => return result,
         }
     }
+}
+
+/// Retries transient read errors. A failed `read` consumes nothing, so
+/// reading again picks up where it left off. Directories are read inside the
+/// tar builder and aren't covered.
+struct Retrying<R> {
+    inner: R,
+    retries: u32,
+}
+
+impl<R: Read> Read for Retrying<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Retrying { inner, retries } = self;
+        with_retry(retries, || inner.read(buf))
+    }
+}
+
+/// A folder (or anything else that isn't a file) where the artifact should go
+/// is never a previous artifact, so it's an error rather than "Already compressed".
+fn check_output_free(output_path: &Path) -> Result<()> {
+    match std::fs::symlink_metadata(output_path) {
+        Ok(m) if !m.is_file() => Err(anyhow::anyhow!(
+            "Output path {} is already taken by a {}; move it aside and retry",
+            output_path.display(),
+            if m.is_dir() { "directory" } else { "non-file" },
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Moves the finished temp file into place without replacing anything that
+/// appeared at `output_path` while compressing (`rename` would silently
+/// clobber a file, or fail oddly on a directory).
+fn place_artifact(temp_path: &Path, output_path: &Path) -> Result<()> {
+    place_new(temp_path, output_path)
+        .map_err(|e| e.context(format!("{} appeared while compressing; not overwriting it", output_path.display())))
+}
+
+/// Renames `temp_path` to `output_path` unless that name is taken. The temp
+/// file is gone afterwards either way.
+fn place_new(temp_path: &Path, output_path: &Path) -> Result<()> {
+    // A hard link fails atomically if the name is taken
+    let placed = match std::fs::hard_link(temp_path, output_path) {
+        Ok(()) => std::fs::remove_file(temp_path).map_err(anyhow::Error::from),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Err(e.into()),
+        // No hard links on this filesystem (e.g. FAT); check, then rename
+        Err(_) if output_path.exists() => Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists).into()),
+        Err(_) => std::fs::rename(temp_path, output_path).map_err(anyhow::Error::from),
+    };
+    if placed.is_err() {
+        let _ = std::fs::remove_file(temp_path);
+    }
+    placed
+}
+
+fn prepare_output_dir(output_path: &Path, opts: &CompressOptions) -> Result<()> {
+    if opts.output_dir.is_some()
+        && let Some(parent) = output_path.parent()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
+/// Every artifact gets a sidecar, so restore can find the source's home even
+/// after the artifact is moved or was written to an output_dir.
+/// `original_mtime` is taken before compressing, as the original may be gone by now.
+fn record_origin(input_path: &Path, stats: &CompressionStats, inner_path: Option<PathBuf>, original_mtime: Option<SystemTime>) -> Result<()> {
+    if stats.output_path != input_path {
+        let original_path = std::path::absolute(input_path)?;
+        let artifact_path = Some(std::path::absolute(&stats.output_path)?);
+        let archived_at = modified_time(&stats.output_path);
+        Sidecar { original_path, artifact_path, inner_path, archived_at, original_mtime }.write(&stats.output_path)?;
+    }
+    Ok(())
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Sets `path`'s mtime, file or folder. Best effort: a restore that worked
+/// isn't failed over a timestamp.
+fn stamp_mtime(path: &Path, time: SystemTime) {
+    let _ = File::open(path).and_then(|f| f.set_modified(time));
+}
 
-    finalize_compression(input_path, &output_path, &temp_path, original_size)
+/// The file a folder holds when that's all it holds: no other files,
+/// subfolders or links. Such a folder is compressed without a tar wrapper.
+fn lone_file(dir: &Path) -> Option<PathBuf> {
+    let mut entries = std::fs::read_dir(dir).ok()?;
+    let entry = entries.next()?.ok()?;
+    if entries.next().is_some() || !entry.file_type().ok()?.is_file() {
+        return None;
+    }
+    Some(entry.path())
 }
 
-fn compress_directory(input_path: &Path, level: i32) -> Result<CompressionStats> {
+fn compress_directory(input_path: &Path, opts: &CompressOptions, progress: Option<Progress>) -> Result<CompressionStats> {
     // Calculate total size first for stats (recursive)
     let original_size = get_dir_size(input_path);
+    if original_size == 0 {
+        return Ok(CompressionStats::skipped(input_path, 0, EMPTY_REASON));
+    }
 
-    let parent = input_path.parent().unwrap_or(Path::new("."));
-    let dirname = input_path.file_name().ok_or(anyhow::anyhow!("Invalid directory name"))?;
+    input_path.file_name().ok_or(anyhow::anyhow!("Invalid directory name"))?;
     
     // Output: folder.tar.zst
-    let output_path = input_path.with_extension("tar.zst"); 
-    // Just appending .tar.zst to "folder" gives "folder.tar.zst" if path is "folder".
-    // Wait, PathBuf::from("folder").with_extension("tar.zst") replaces extension? 
-    // No, "folder" has no extension. So it becomes "folder.tar.zst".
-    // If path is "folder.v1", it becomes "folder.tar.zst".
-    // Let's ensure we preserve the name.
-    let output_path = PathBuf::from(format!("{}.tar.zst", input_path.to_string_lossy()));
+    let output_path = output_path_for(input_path, true, opts);
+    check_output_free(&output_path)?;
+    if output_path.exists() {
+        // Re-runs (e.g. nightly cron) must not re-tar over an earlier archive
+        return Ok(CompressionStats::skipped(input_path, original_size, "Already compressed"));
+    }
+
+    // One file in a folder: tar headers would only add bytes
+    if let Some(file) = lone_file(input_path) {
+        return compress_file_as(&file, input_path, opts, original_size, progress);
+    }
 
     let temp_path = output_path.with_extension("tmp");
+    prepare_output_dir(&output_path, opts)?;
+    let original_mtime = modified_time(input_path);
 
-    let file = File::create(&temp_path)?;
-    let encoder = zstd::stream::write::Encoder::new(file, level)?;
-    let mut tar = tar::Builder::new(encoder);
+    let checksum = run_stage(Stage::Compress, &temp_path, || {
+        encode_tree(input_path, File::create(&temp_path)?, opts, progress)
+    })?;
 
-    // Append dir recursively
-    // We want the archive to contain the directory itself, so when unpacking it creates the directory.
-    // append_dir_all("name_in_archive", "path_on_disk")
-    tar.append_dir_all(dirname, input_path)?;
-    
-    // Finish Tar
-    let encoder = tar.into_inner()?;
-    // Finish Zstd
-    encoder.finish()?;
+    let stats = finalize_compression(input_path, &output_path, &temp_path, original_size, checksum, opts)?;
+    record_origin(input_path, &stats, None, original_mtime)?;
+    Ok(stats)
+}
 
-    // Finalize
-    // For directories, we use trash::delete or fs::remove_dir_all
-    // But finalize_compression checks size savings.
+/// `tar::Builder::append_dir_all`, one entry at a time so progress can be
+/// reported per file. Walks in the same order and writes the same headers, so
+/// the archive is identical to what `append_dir_all` would produce.
+/// `follow` must match the builder's `follow_symlinks` setting.
+fn append_tree<W: Write>(tar: &mut tar::Builder<W>, name: &Path, src_root: &Path, follow: bool, progress: Option<Progress>) -> Result<()> {
+    // Collect first so the total is known before the first entry is written
+    let mut entries = Vec::new();
+    let mut stack = vec![(src_root.to_path_buf(), true)];
+    while let Some((src, is_dir)) = stack.pop() {
+        // A followed symlink to a directory is walked like the directory itself
+        if is_dir || (follow && src.is_dir()) {
+            if follow && is_symlink_cycle(src_root, &src) {
+                return Err(anyhow::anyhow!("Symlink cycle at {}; turn off dereference_symlinks", src.display()));
+            }
+            for entry in std::fs::read_dir(&src)? {
+                let entry = entry?;
+                let is_dir = entry.file_type()?.is_dir();
+                stack.push((entry.path(), is_dir));
+            }
+            entries.push((src, true));
+        } else {
+            entries.push((src, false));
+        }
+    }
 
-    let compressed_size = temp_path.metadata()?.len();
+    let total = entries.iter().filter(|(_, is_dir)| !is_dir).count() as u64;
+    let mut added = 0;
+    for (src, is_dir) in entries {
+        let dest = name.join(src.strip_prefix(src_root)?);
+        tar.append_path_with_name(&src, &dest)?;
+        if !is_dir {
+            added += 1;
+            if let Some(report) = progress {
+                report(ProgressUpdate::Files { added, total });
+            }
+        }
+    }
+    Ok(())
+}
 
-    if compressed_size < original_size {
-         std::fs::rename(&temp_path, &output_path)?;
-         // Use trash if available, or remove_dir_all?
-         // App usually handles deletion of original checks, wait.
-         // In compress_single_file below, I see `std::fs::remove_file(input_path)?`.
-         // For directories, we should be careful. `std::fs::remove_dir_all`.
-         std::fs::remove_dir_all(input_path)?;
-         
-         Ok(CompressionStats {
-             original_size,
-             compressed_size,
-             output_path,
-         })
+/// True when `dir` resolves to itself or a folder above it within the walk,
+/// which following would recurse into forever.
+fn is_symlink_cycle(src_root: &Path, dir: &Path) -> bool {
+    let Ok(target) = dir.canonicalize() else { return false };
+    dir.ancestors()
+        .skip(1)
+        .take_while(|a| a.starts_with(src_root))
+        .filter_map(|a| a.canonicalize().ok())
+        .any(|a| a == target)
+}
+
+/// Passes reads or writes through, reporting the running byte count.
+struct Counting<'a, T> {
+    inner: T,
+    total: u64,
+    progress: Option<Progress<'a>>,
+}
+
+impl<'a, T> Counting<'a, T> {
+    fn new(inner: T, progress: Option<Progress<'a>>) -> Self {
+        Self { inner, total: 0, progress }
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.total += n as u64;
+        if let Some(report) = self.progress
+            && n > 0
+        {
+            report(ProgressUpdate::Bytes(self.total));
+        }
+    }
+}
+
+impl<T: Read> Read for Counting<'_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.advance(n);
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Counting<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.advance(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Gets the source out of the way once its artifact is in place: kept,
+/// moved into the backup store, or deleted.
+fn remove_original(input_path: &Path, opts: &CompressOptions) -> Result<()> {
+    if opts.keep_original {
+        return Ok(());
+    }
+    if let Some(dir) = &opts.backup_dir {
+        backup::move_into(dir, input_path)?;
+    } else if input_path.is_dir() {
+        std::fs::remove_dir_all(input_path)?;
     } else {
-        let _ = std::fs::remove_file(&temp_path);
-        Ok(CompressionStats {
-             original_size,
-             compressed_size: original_size, 
-             output_path: input_path.to_path_buf(),
-        })
+        std::fs::remove_file(input_path)?;
+    }
+    // On Windows, deleting a file another program opened with delete sharing
+    // only marks it; it lingers until they close it
+    if input_path.symlink_metadata().is_ok() {
+        return Err(anyhow::anyhow!("{} is still there after removing it; is it open in another program?", input_path.display()));
+    }
+    Ok(())
+}
+
+/// Encodes `reader` into `writer` as zstd at `level`. Returns the checksum
+/// of what was read when `opts.verify` is on.
+fn encode_stream<R: Read, W: Write>(reader: R, writer: W, level: i32, opts: &CompressOptions) -> Result<Option<u64>> {
+    let mut reader = Checksum::new(reader, opts.verify);
+    let mut encoder = opts.backend.encoder(writer, level)?;
+    std::io::copy(&mut reader, &mut encoder)?;
+    encoder.finish()?.flush()?;
+    Ok(reader.sum())
+}
+
+/// Writes the folder `input_path` into `writer` as a zstd-compressed tar.
+/// Returns the checksum of the tar stream (what decoding gives back) when
+/// `opts.verify` is on.
+fn encode_tree<W: Write>(input_path: &Path, writer: W, opts: &CompressOptions, progress: Option<Progress>) -> Result<Option<u64>> {
+    let dirname = input_path.file_name().ok_or(anyhow::anyhow!("Invalid directory name"))?;
+    let encoder = opts.backend.encoder(writer, level_for(input_path, opts))?;
+    let mut tar = tar::Builder::new(Counting::new(Checksum::new(encoder, opts.verify), progress));
+    tar.follow_symlinks(opts.dereference_symlinks);
+
+    // We want the archive to contain the directory itself, so when unpacking it creates the directory.
+    append_tree(&mut tar, Path::new(dirname), input_path, opts.dereference_symlinks, progress)?;
+
+    // Finish Tar, then Zstd
+    let hashed = tar.into_inner()?.inner;
+    let checksum = hashed.sum();
+    hashed.inner.finish()?.flush()?;
+    Ok(checksum)
+}
+
+/// Streams `input_path` into `writer` without touching anything on disk: a
+/// file as plain `.zst`, a folder as `.tar.zst` (always tarred, so the format
+/// doesn't depend on what the folder holds). Returns the input size.
+pub fn compress_to_writer<W: Write>(input_path: &Path, writer: W, opts: &CompressOptions) -> Result<u64> {
+    if input_path.is_dir() {
+        encode_tree(input_path, writer, opts, None)?;
+        Ok(get_dir_size(input_path))
+    } else {
+        let input = File::open(input_path)?;
+        let size = input.metadata()?.len();
+        encode_stream(BufReader::new(input), writer, level_for(input_path, opts), opts)?;
+        Ok(size)
+    }
+}
+
+/// Steps from source to artifact, in the order they run. Each starts only
+/// once the one before succeeded; a failure up to `Place` removes the temp
+/// file and leaves the source exactly as it was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Encode the source into a temp file (checksumming the input when verifying).
+    Compress,
+    /// Decode the temp file and compare checksums; only with `verify`.
+    Verify,
+    /// Move the temp file to the artifact name, never over an existing file.
+    Place,
+    /// Keep, back up or delete the source, per the options.
+    RemoveOriginal,
+}
+
+impl Stage {
+    /// Context for an error at this stage, saying what state things are in.
+    fn failure(self) -> &'static str {
+        match self {
+            Stage::Compress => "Compression failed; original left in place",
+            Stage::Verify => "Verification failed; original left in place",
+            Stage::Place => "Could not place the artifact; original left in place",
+            Stage::RemoveOriginal => "Artifact is complete, but the original could not be removed",
+        }
+    }
+}
+
+/// Runs one `Stage`, cleaning up the temp file if it fails before the
+/// artifact is in place.
+fn run_stage<T>(stage: Stage, temp_path: &Path, op: impl FnOnce() -> Result<T>) -> Result<T> {
+    fail_point(stage).and_then(|()| op()).map_err(|e| {
+        if matches!(stage, Stage::Compress | Stage::Verify | Stage::Place) {
+            let _ = std::fs::remove_file(temp_path);
+        }
+        e.context(stage.failure())
+    })
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Stage that `fail_point` makes fail, for tests.
+    static FAIL_AT: std::cell::Cell<Option<Stage>> = const { std::cell::Cell::new(None) };
+    /// OS error code the injected failure carries, if any.
+    static FAIL_OS_ERROR: std::cell::Cell<Option<i32>> = const { std::cell::Cell::new(None) };
+}
+
+/// Injected failure for tests; a no-op otherwise.
+fn fail_point(stage: Stage) -> Result<()> {
+    #[cfg(test)]
+    if FAIL_AT.with(|f| f.get()) == Some(stage) {
+        if let Some(code) = FAIL_OS_ERROR.with(|f| f.get()) {
+            return Err(std::io::Error::from_raw_os_error(code).into());
+        }
+        return Err(anyhow::anyhow!("Injected failure at {:?}", stage));
+    }
+    let _ = stage;
+    Ok(())
+}
+
+/// Hashes what passes through, when enabled. Not cryptographic: it guards
+/// against corruption, not tampering.
+struct Checksum<T> {
+    inner: T,
+    hasher: Option<std::hash::DefaultHasher>,
+}
+
+impl<T> Checksum<T> {
+    fn new(inner: T, enabled: bool) -> Self {
+        Self { inner, hasher: enabled.then(std::hash::DefaultHasher::new) }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        if let Some(hasher) = &mut self.hasher {
+            std::hash::Hasher::write(hasher, bytes);
+        }
+    }
+
+    fn sum(&self) -> Option<u64> {
+        self.hasher.as_ref().map(std::hash::Hasher::finish)
+    }
+}
+
+impl<T: Read> Read for Checksum<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Checksum<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decodes `temp_path` in full and checks it hashes to `expected`.
+fn verify_artifact(temp_path: &Path, expected: u64) -> Result<()> {
+    let mut decoder = zstd::stream::read::Decoder::new(File::open(temp_path)?)?;
+    let mut decoded = Checksum::new(std::io::sink(), true);
+    std::io::copy(&mut decoder, &mut decoded)?;
+    if decoded.sum() != Some(expected) {
+        return Err(anyhow::anyhow!("Checksum mismatch: {} does not decode to the original", temp_path.display()));
     }
+    Ok(())
 }
 
-fn finalize_compression(input_path: &Path, output_path: &Path, temp_path: &Path, original_size: u64) -> Result<CompressionStats> {
+/// Runs the stages after `Compress`. `checksum` is set when verifying.
+fn finalize_compression(input_path: &Path, output_path: &Path, temp_path: &Path, original_size: u64, checksum: Option<u64>, opts: &CompressOptions) -> Result<CompressionStats> {
     let compressed_size = temp_path.metadata()?.len();
 
     if compressed_size < original_size {
-        std::fs::rename(temp_path, output_path)?;
-        std::fs::remove_file(input_path)?;
-        
+        if let Some(expected) = checksum {
+            run_stage(Stage::Verify, temp_path, || verify_artifact(temp_path, expected))?;
+        }
+        run_stage(Stage::Place, temp_path, || place_artifact(temp_path, output_path))?;
+        // A locked file was not touched, so drop the artifact rather than keep two
+        // copies. A folder may be half removed by then; its artifact has to stay.
+        if let Err(e) = fail_point(Stage::RemoveOriginal).and_then(|()| remove_original(input_path, opts)) {
+            if is_sharing_violation(&e) && input_path.is_file() {
+                let _ = std::fs::remove_file(output_path);
+                return Err(e.context(LOCKED_REASON));
+            }
+            return Err(e.context(Stage::RemoveOriginal.failure()));
+        }
+
         Ok(CompressionStats {
             original_size,
             compressed_size,
             output_path: output_path.to_path_buf(),
+            skipped: None,
+            retries: 0,
         })
     } else {
         let _ = std::fs::remove_file(temp_path);
@@ -121,6 +938,8 @@ fn finalize_compression(input_path: &Path, output_path: &Path, temp_path: &Path,
             original_size,
             compressed_size: original_size, 
             output_path: input_path.to_path_buf(),
+            skipped: None,
+            retries: 0,
         })
     }
 }
@@ -136,69 +955,395 @@ fn get_dir_size(path: &Path) -> u64 {
 }
 
 
-pub fn decompress_file(input_path: &Path) -> Result<u64> {
-    let file_name = input_path.file_name().unwrap_or_default().to_string_lossy();
+/// How far an artifact may expand on restore, as a multiple of its
+/// size on disk. Real trees rarely pass a few hundred; a crafted archive can
+/// reach millions.
+pub const DEFAULT_MAX_EXPANSION: u64 = 1000;
 
-    if file_name.ends_with(".tar.zst") {
-        decompress_archive(input_path)
-    } else if input_path.extension().map_or(false, |ext| ext == "zst") {
-        decompress_single(input_path)
-    } else {
-        Err(anyhow::anyhow!("File is not a supported archive"))
+/// Restores up to this size pass whatever their expansion: a small log of
+/// repeated lines can outdo any sane ratio, and can't fill a disk.
+const MIN_EXPANSION_LIMIT: u64 = 1024 * 1024;
+
+/// Settings for one restore.
+#[derive(Debug, Clone)]
+pub struct RestoreOptions {
+    /// Leave the artifact and its sidecar in place, so the restored copy can
+    /// be worked on while the archive remains as a backup.
+    pub keep_archive: bool,
+    pub naming: Naming,
+    /// Abort a restore once it decodes to more than this many times the
+    /// archive's size; `None` for no limit. Free disk space is always a limit.
+    pub max_expansion: Option<u64>,
+    /// Stamp the restored item with the archive's creation time rather than
+    /// its original mtime, so incremental builds treat it as up to date.
+    pub match_archive_mtime: bool,
+}
+
+impl Default for RestoreOptions {
+    fn default() -> Self {
+        RestoreOptions { keep_archive: false, naming: Naming::default(), max_expansion: Some(DEFAULT_MAX_EXPANSION), match_archive_mtime: false }
     }
 }
 
-fn decompress_single(input_path: &Path) -> Result<u64> {
-     let input_file = File::open(input_path)?;
-    let reader = BufReader::new(input_file);
+/// Restores an artifact with the default options; see `decompress_with`.
+pub fn decompress_file(input_path: &Path) -> Result<u64> {
+    decompress_with(input_path, true, &RestoreOptions::default())
+}
 
-    let output_path = input_path.with_extension(""); // Removes .zst
-    
-    let output_file = File::create(&output_path)?;
-    let writer = BufWriter::new(output_file);
+/// Restores next to the artifact, ignoring where its sidecar says it came from.
+pub fn decompress_in_place(input_path: &Path) -> Result<u64> {
+    decompress_with(input_path, false, &RestoreOptions::default())
+}
 
-    zstd::stream::copy_decode(reader, writer)?;
+/// `decompress_file` (`to_original`) or `decompress_in_place`, with `opts`.
+pub fn decompress_with(input_path: &Path, to_original: bool, opts: &RestoreOptions) -> Result<u64> {
+    let naming = &opts.naming;
+    let (_, is_dir) = naming.source_name(input_path)
+        .ok_or_else(|| anyhow::anyhow!("File is not a supported archive"))?;
+    let output_path = if to_original { restore_path(input_path, naming) } else { local_restore_path(input_path, naming) };
+    decompress_to(input_path, &output_path, is_dir, opts)
+}
 
-    let restored_size = output_path.metadata()?.len();
-    std::fs::remove_file(input_path)?;
-    
-    Ok(restored_size)
+/// Where `decompress_file` puts the restored item: the original location
+/// recorded in the sidecar, else next to the artifact.
+pub fn restore_path(input_path: &Path, naming: &Naming) -> PathBuf {
+    match Sidecar::read(input_path) {
+        Some(sidecar) => sidecar.original_path,
+        None => local_restore_path(input_path, naming),
+    }
 }
 
-fn decompress_archive(input_path: &Path) -> Result<u64> {
-    let file = File::open(input_path)?;
-    let decoder = zstd::stream::read::Decoder::new(file)?;
-    let mut archive = tar::Archive::new(decoder);
+/// `foo.log.zst` -> `foo.log`, `foo.tar.zst` -> `foo` (by default), in the artifact's folder.
+fn local_restore_path(input_path: &Path, naming: &Naming) -> PathBuf {
+    match naming.source_name(input_path) {
+        Some((name, _)) => input_path.with_file_name(name),
+        None => input_path.with_extension(""),
+    }
+}
 
-    // Unpack to parent directory
-    let parent = input_path.parent().unwrap_or(Path::new("."));
-    archive.unpack(parent)?;
+fn decompress_to(input_path: &Path, output_path: &Path, is_dir: bool, opts: &RestoreOptions) -> Result<u64> {
+    let sidecar = Sidecar::read(input_path);
+    let top_path = output_path;
+    // A folder that held a single file comes back as that folder
+    let output_path = &match sidecar.as_ref().and_then(|s| s.inner_path.as_ref()) {
+        Some(inner) => output_path.join(inner),
+        None => output_path.to_path_buf(),
+    };
 
-    // We can't easily get strict restored size without calculation, 
-    // but we can assume success if unpack didn't fail.
-    // Let's try to calculate size of what we just unpacked?
-    // It's a directory. The dirname should be what was inside.
-    // Usually input is name.tar.zst -> name.
-    
-    let folder_name = input_path.file_stem().map(|s| {
-         // remove .tar from .tar.zst stem?
-         // file_stem of 'foo.tar.zst' is 'foo.tar'.
-         Path::new(s).file_stem().unwrap_or(s)
-    }).unwrap_or_default();
-    
-    let restored_path = parent.join(folder_name);
-    let restored_size = get_dir_size(&restored_path); // Approximation
-    
-    std::fs::remove_file(input_path)?;
+    // Whatever sits there now may be newer than the archive
+    if output_path.exists() {
+        anyhow::bail!("{} already exists; not restoring over it", output_path.display());
+    }
 
-    Ok(restored_size)
-}
+    // The original folder may be gone if things were moved around since
+    if let Some(parent) = output_path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
 
-#[cfg(test)]
-mod tests {
+    let restored_size = if is_dir {
+        decompress_archive(input_path, output_path, opts.max_expansion)?
+    } else {
+        decompress_single(input_path, output_path, opts.max_expansion)?
+    };
+
+    // Last, as unpacking into a folder bumps its mtime
+    let mtime = sidecar.and_then(|s| if opts.match_archive_mtime { s.archived_at } else { s.original_mtime });
+    if let Some(mtime) = mtime {
+        stamp_mtime(top_path, mtime);
+    }
+
+    if !opts.keep_archive {
+        std::fs::remove_file(input_path)?;
+        Sidecar::remove(input_path);
+    }
+    Ok(restored_size)
+}
+
+fn decompress_single(input_path: &Path, output_path: &Path, max_expansion: Option<u64>) -> Result<u64> {
+    let limit = restore_limit(input_path, output_path, max_expansion)?;
+    decode_stream(BufReader::new(File::open(input_path)?), output_path, limit)
+}
+
+fn decompress_archive(input_path: &Path, restored_path: &Path, max_expansion: Option<u64>) -> Result<u64> {
+    // The archive holds the directory itself (under its original name), so
+    // unpack into the parent of where it should end up.
+    let parent = parent_dir(restored_path);
+    let limit = restore_limit(input_path, restored_path, max_expansion)?;
+
+    let existed = restored_path.exists();
+    if let Err(e) = unpack_stream(File::open(input_path)?, parent, limit) {
+        // Don't leave half a tree behind, but never touch a folder that was already there
+        if !existed {
+            let _ = std::fs::remove_dir_all(restored_path);
+        }
+        return Err(e);
+    }
+
+    Ok(get_dir_size(restored_path)) // Approximation
+}
+
+/// `path`'s folder, `.` for a bare name.
+fn parent_dir(path: &Path) -> &Path {
+    path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+/// Most bytes restoring `input_path` to `output_path` may write: its
+/// `max_expansion` multiple (but at least `MIN_EXPANSION_LIMIT`), capped by
+/// the free space where it lands.
+fn restore_limit(input_path: &Path, output_path: &Path, max_expansion: Option<u64>) -> Result<Option<u64>> {
+    let archive_size = std::fs::metadata(input_path)?.len();
+    let expansion = max_expansion.map(|ratio| archive_size.saturating_mul(ratio).max(MIN_EXPANSION_LIMIT));
+    Ok([expansion, available_space(parent_dir(output_path))]
+        .into_iter()
+        .flatten()
+        .min())
+}
+
+/// Free bytes on the filesystem holding `dir`, as an unprivileged user may
+/// fill them; `None` where that can't be told, which leaves only the
+/// expansion limit.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // statvfs field widths vary by platform
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and outlives the call; `stat` is only
+    // read once statvfs has reported filling it in.
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+#[cfg(not(unix))]
+fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+fn limit_exceeded(limit: u64) -> anyhow::Error {
+    anyhow::anyhow!("Extraction aborted: size limit exceeded ({} bytes allowed)", limit)
+}
+
+/// Decodes a plain `.zst` stream into the file `output_path`, giving up once
+/// it passes `limit` bytes. Returns its size. Decodes into a temp file next
+/// to it first, so a failure leaves nothing behind and nothing is replaced.
+fn decode_stream<R: Read>(reader: R, output_path: &Path, limit: Option<u64>) -> Result<u64> {
+    let temp_path = persist::sibling(output_path, "tmp");
+    let decoded = (|| {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        let decoder = zstd::stream::read::Decoder::new(reader)?;
+        // One byte past the limit is enough to tell it was exceeded
+        let size = std::io::copy(&mut decoder.take(limit.map_or(u64::MAX, |l| l.saturating_add(1))), &mut writer)?;
+        if let Some(limit) = limit
+            && size > limit
+        {
+            return Err(limit_exceeded(limit));
+        }
+        writer.flush()?;
+        Ok(size)
+    })();
+    let size = decoded.inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp_path);
+    })?;
+    place_new(&temp_path, output_path)
+        .map_err(|e| e.context(format!("{} already exists; not overwriting it", output_path.display())))?;
+    Ok(size)
+}
+
+/// Unpacks a `.tar.zst` stream into `dir`, giving up once its entries add up
+/// to more than `limit` bytes. Returns the tar stream's size.
+fn unpack_stream<R: Read>(reader: R, dir: &Path, limit: Option<u64>) -> Result<u64> {
+    let decoder = Counting::new(zstd::stream::read::Decoder::new(reader)?, None);
+    let mut archive = tar::Archive::new(decoder);
+    match limit {
+        Some(limit) => unpack_limited(&mut archive, dir, limit)?,
+        None => archive.unpack(dir)?,
+    }
+    Ok(archive.into_inner().total)
+}
+
+/// `tar::Archive::unpack`, counting each entry's size against `limit` before
+/// writing it. Directories are still applied last, deepest first, so their
+/// permissions can't get in the way of what goes inside.
+fn unpack_limited<R: Read>(archive: &mut tar::Archive<R>, dir: &Path, limit: u64) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut extracted = 0u64;
+    let mut directories = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        extracted = extracted.saturating_add(entry.size());
+        if extracted > limit {
+            return Err(limit_exceeded(limit));
+        }
+        if entry.header().entry_type() == tar::EntryType::Directory {
+            directories.push(entry);
+        } else {
+            entry.unpack_in(dir)?;
+        }
+    }
+    directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut entry in directories {
+        entry.unpack_in(dir)?;
+    }
+    Ok(())
+}
+
+/// What a stream holds. Files carry it in their name; a pipe can't be
+/// sniffed without consuming it, so stream readers are told.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// One compressed file (`.zst`).
+    Zst,
+    /// A compressed folder (`.tar.zst`).
+    TarZst,
+}
+
+impl StreamFormat {
+    /// From `"zst"` or `"tar.zst"`, with or without the leading dot.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim_start_matches('.') {
+            "zst" => Some(StreamFormat::Zst),
+            "tar.zst" => Some(StreamFormat::TarZst),
+            _ => None,
+        }
+    }
+}
+
+/// Restores a stream such as stdin: `.zst` to the file `output`, `.tar.zst`
+/// unpacked into the directory `output` (created if needed). Returns the
+/// bytes decoded.
+pub fn decompress_reader<R: Read>(reader: R, format: StreamFormat, output: &Path) -> Result<u64> {
+    match format {
+        StreamFormat::Zst => {
+            if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            decode_stream(reader, output, available_space(parent_dir(output)))
+        }
+        StreamFormat::TarZst => {
+            std::fs::create_dir_all(output)?;
+            unpack_stream(reader, output, available_space(output))
+        }
+    }
+}
+
+/// Read-only listing of what a restore would produce: (entry name, size).
+/// For a folder's archive this walks the tar headers; for a file it is the
+/// would-be output name and its uncompressed size.
+pub fn list_archive(input_path: &Path, naming: &Naming) -> Result<Vec<(String, u64)>> {
+    let Some((source_name, is_dir)) = naming.source_name(input_path) else {
+        return Err(anyhow::anyhow!("File is not a supported archive"));
+    };
+
+    if is_dir {
+        let file = File::open(input_path)?;
+        let decoder = zstd::stream::read::Decoder::new(file)?;
+        let mut archive = tar::Archive::new(decoder);
+
+        let mut entries = Vec::new();
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let name = entry.path()?.to_string_lossy().to_string();
+            entries.push((name, entry.header().size()?));
+        }
+        Ok(entries)
+    } else {
+        // The streaming encoder doesn't record the content size in the frame
+        // header, so decode into a sink to measure it.
+        let file = File::open(input_path)?;
+        let mut decoder = zstd::stream::read::Decoder::new(file)?;
+        let size = std::io::copy(&mut decoder, &mut std::io::sink())?;
+
+        let mut output_name = source_name;
+        if let Some(inner) = Sidecar::read(input_path).and_then(|s| s.inner_path) {
+            output_name = format!("{}/{}", output_name, inner.display());
+        }
+        Ok(vec![(output_name, size)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
     use std::io::Write;
 
+    /// Removes an artifact along with its sidecar.
+    fn remove_artifact(path: &Path) -> Result<()> {
+        std::fs::remove_file(path)?;
+        Sidecar::remove(path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_protected_paths_are_never_compressed() -> Result<()> {
+        let dir = PathBuf::from("test_protected_dir");
+        std::fs::create_dir_all(&dir)?;
+        let input = dir.join("id_rsa");
+        std::fs::write(&input, "secret ".repeat(1000))?;
+
+        let opts = CompressOptions { protected_paths: vec![dir.clone()], ..Default::default() };
+        let stats = compress_with(&input, &opts)?;
+        let untouched = input.exists() && !output_path_for(&input, false, &opts).exists();
+        std::fs::remove_dir_all(&dir)?;
+
+        assert!(stats.skipped.as_deref().is_some_and(|r| r.starts_with("Protected path")));
+        assert!(untouched);
+        if let Some(home) = dirs::home_dir() {
+            assert!(protected_reason(&home, &[]).is_some());
+            assert_eq!(expand_home("~/.ssh"), home.join(".ssh"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_recently_modified_folders_are_left_alone() -> Result<()> {
+        let dir = PathBuf::from("test_recent_dir");
+        std::fs::create_dir_all(dir.join("deps"))?;
+        std::fs::write(dir.join("a.log"), "built ".repeat(1000))?;
+        std::fs::write(dir.join("deps/b.log"), "still building ".repeat(1000))?;
+
+        let opts = CompressOptions { protect_recent: Some(Duration::from_secs(60)), ..Default::default() };
+        let stats = compress_with(&dir, &opts)?;
+        let untouched = dir.join("deps/b.log").exists() && !output_path_for(&dir, true, &opts).exists();
+        let settled = compress_with(&dir, &CompressOptions { protect_recent: Some(Duration::ZERO), ..opts });
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_file("test_recent_dir.tar.zst");
+        Sidecar::remove(Path::new("test_recent_dir.tar.zst"));
+
+        assert_eq!(stats.skipped.as_deref(), Some(RECENT_REASON));
+        assert!(untouched);
+        assert!(settled?.skipped.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_files_are_left_alone() -> Result<()> {
+        let path = PathBuf::from("test_sparse.img");
+        let file = File::create(&path)?;
+        file.set_len(64 * 1024 * 1024)?;
+        (&file).write_all(b"boot sector")?;
+        drop(file);
+        if !spyder::is_sparse(&path.metadata()?) {
+            // The filesystem allocated the holes; nothing to check here
+            std::fs::remove_file(&path)?;
+            return Ok(());
+        }
+
+        let stats = compress_file(&path, DEFAULT_LEVEL)?;
+        let untouched = path.exists() && !PathBuf::from("test_sparse.img.zst").exists();
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(stats.skipped.as_deref(), Some(SPARSE_REASON));
+        assert!(untouched);
+        Ok(())
+    }
+
     #[test]
     fn test_compress_saves_space() -> Result<()> {
         // Setup: Create compressible file
@@ -220,7 +1365,598 @@ mod tests {
         assert!(path.with_extension("log.zst").exists(), "Compressed file should exist");
 
         // Cleanup
-        std::fs::remove_file(path.with_extension("log.zst"))?;
+        remove_artifact(&path.with_extension("log.zst"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_reports_progress() -> Result<()> {
+        // Setup
+        let path = PathBuf::from("test_progress.log");
+        std::fs::write(&path, vec![b'A'; 1024 * 1024])?;
+        let seen = std::sync::atomic::AtomicU64::new(0);
+        let report = |update: ProgressUpdate| {
+            if let ProgressUpdate::Bytes(total) = update {
+                seen.store(total, std::sync::atomic::Ordering::Relaxed);
+            }
+        };
+
+        // Act
+        let stats = compress_with_progress(&path, &CompressOptions { level: 3, ..Default::default() }, Some(&report))?;
+
+        // Assert: the last report covers the whole input
+        assert_eq!(seen.into_inner(), stats.original_size);
+
+        // Cleanup
+        remove_artifact(&path.with_extension("log.zst"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tar_walk_matches_append_dir_all() -> Result<()> {
+        // Setup: a small tree with nesting and an empty folder
+        let dir = PathBuf::from("test_tar_walk_dir");
+        std::fs::create_dir_all(dir.join("a/b"))?;
+        std::fs::create_dir_all(dir.join("empty"))?;
+        std::fs::write(dir.join("top.log"), b"top")?;
+        std::fs::write(dir.join("a/one.txt"), vec![b'1'; 4096])?;
+        std::fs::write(dir.join("a/b/two.txt"), b"two")?;
+
+        // Act
+        let mut expected = tar::Builder::new(Vec::new());
+        expected.append_dir_all("test_tar_walk_dir", &dir)?;
+        let expected = expected.into_inner()?;
+
+        let files = std::sync::Mutex::new(Vec::new());
+        let report = |update: ProgressUpdate| {
+            if let ProgressUpdate::Files { added, total } = update {
+                files.lock().unwrap().push((added, total));
+            }
+        };
+        let mut walked = tar::Builder::new(Vec::new());
+        append_tree(&mut walked, Path::new("test_tar_walk_dir"), &dir, true, Some(&report))?;
+        let walked = walked.into_inner()?;
+
+        // Assert
+        assert!(expected == walked, "Archives should be byte-for-byte identical");
+        assert_eq!(files.into_inner().unwrap(), vec![(1, 3), (2, 3), (3, 3)]);
+
+        // Cleanup
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_symlinks_stored_or_dereferenced() -> Result<()> {
+        for dereference in [false, true] {
+            // Setup: a folder holding a file and a link to it
+            let dir = PathBuf::from(format!("test_symlink_dir_{}", dereference));
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(dir.join("real.log"), vec![b'A'; 64 * 1024])?;
+            std::os::unix::fs::symlink("real.log", dir.join("link.log"))?;
+            let opts = CompressOptions { level: 3, dereference_symlinks: dereference, ..Default::default() };
+
+            // Act
+            let stats = compress_with(&dir, &opts)?;
+            let entries = list_archive(&stats.output_path, &Naming::default())?;
+
+            // Assert: the link is either a link entry (no data) or a full copy
+            let link_size = entries.iter().find(|(name, _)| name.ends_with("link.log")).map(|(_, size)| *size);
+            assert_eq!(link_size, Some(if dereference { 64 * 1024 } else { 0 }));
+
+            // Restore brings the link back as it was archived
+            decompress_file(&stats.output_path)?;
+            assert_eq!(dir.join("link.log").symlink_metadata()?.file_type().is_symlink(), !dereference);
+
+            // Cleanup
+            std::fs::remove_dir_all(&dir)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_dereference_refuses_symlink_cycle() -> Result<()> {
+        let dir = PathBuf::from("test_symlink_cycle");
+        std::fs::create_dir_all(dir.join("sub"))?;
+        std::fs::write(dir.join("data.log"), vec![b'A'; 64 * 1024])?;
+        std::os::unix::fs::symlink("..", dir.join("sub/up"))?;
+
+        let opts = CompressOptions { level: 3, dereference_symlinks: true, ..Default::default() };
+        let err = compress_with(&dir, &opts).err().expect("A cycle should be refused");
+
+        assert!(format!("{:#}", err).contains("Symlink cycle"));
+        assert!(dir.join("data.log").exists());
+        assert!(!PathBuf::from("test_symlink_cycle.tar.zst").exists());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_keep_original() -> Result<()> {
+        // Setup: Create compressible file
+        let path = PathBuf::from("test_keep_original.log");
+        std::fs::write(&path, vec![b'A'; 1024 * 1024])?;
+        let opts = CompressOptions { level: 3, keep_original: true, ..Default::default() };
+
+        // Act
+        let stats = compress_with(&path, &opts)?;
+
+        // Assert
+        assert!(stats.compressed_size < stats.original_size, "Stats should reflect the compressed size");
+        assert!(path.exists(), "Original file should be kept");
+        assert!(stats.output_path.exists(), "Compressed file should exist");
+
+        // Cleanup
+        std::fs::remove_file(&path)?;
+        remove_artifact(&stats.output_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_to_output_dir_round_trip() -> Result<()> {
+        // Setup: root/sub/data.log, compressed into a separate destination
+        let root = PathBuf::from("test_output_dir_root");
+        let dest = PathBuf::from("test_output_dir_dest");
+        std::fs::create_dir_all(root.join("sub"))?;
+        let path = root.join("sub/data.log");
+        std::fs::write(&path, vec![b'A'; 64 * 1024])?;
+        let opts = CompressOptions {
+            level: 3,
+            output_dir: Some(dest.clone()),
+            root: Some(root.clone()),
+            ..Default::default()
+        };
+
+        // Act
+        let stats = compress_with(&path, &opts)?;
+
+        // Assert: tree structure preserved under dest, with a sidecar
+        assert_eq!(stats.output_path, dest.join("sub/data.log.zst"));
+        assert_eq!(find_artifact(&path, &opts), stats.output_path);
+        assert!(Sidecar::path_for(&stats.output_path).exists());
+        assert!(!path.exists());
+
+        // Restore goes back to the original location
+        decompress_file(&stats.output_path)?;
+        assert_eq!(std::fs::read(&path)?.len(), 64 * 1024);
+        assert!(!Sidecar::path_for(&stats.output_path).exists());
+
+        // Cleanup
+        std::fs::remove_dir_all(&root)?;
+        std::fs::remove_dir_all(&dest)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_naming_round_trips() -> Result<()> {
+        let dir = PathBuf::from("test_custom_naming");
+        std::fs::create_dir_all(dir.join("deps"))?;
+        std::fs::write(dir.join("deps").join("a.js"), "module ".repeat(5000))?;
+        std::fs::write(dir.join("deps").join("b.js"), "export ".repeat(5000))?;
+        std::fs::write(dir.join("app.log"), "line ".repeat(5000))?;
+        let naming = Naming::new("{name}-piper.{ext}.zst", "{name}.tzst")?;
+        let opts = CompressOptions { level: 3, naming: naming.clone(), ..Default::default() };
+
+        let file = compress_with(&dir.join("app.log"), &opts)?;
+        let folder = compress_with(&dir.join("deps"), &opts)?;
+        assert_eq!(file.output_path, dir.join("app-piper.log.zst"));
+        assert_eq!(folder.output_path, dir.join("deps.tzst"));
+        assert_eq!(list_archive(&file.output_path, &naming)?[0].0, "app.log");
+
+        let restore = RestoreOptions { naming: naming.clone(), ..Default::default() };
+        decompress_with(&file.output_path, false, &restore)?;
+        decompress_with(&folder.output_path, false, &restore)?;
+        let restored = std::fs::read_to_string(dir.join("app.log"))? == "line ".repeat(5000)
+            && dir.join("deps").join("b.js").exists();
+        std::fs::remove_dir_all(&dir)?;
+        assert!(restored);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_aborts_past_the_expansion_limit() -> Result<()> {
+        let dir = PathBuf::from("test_bomb_src");
+        std::fs::create_dir_all(&dir)?;
+        // Zeros squeeze far past 1000:1
+        std::fs::write(dir.join("zeros.bin"), vec![0u8; 8 * 1024 * 1024])?;
+        std::fs::write(dir.join("more.bin"), vec![0u8; 1024])?;
+        let stats = compress_with(&dir, &CompressOptions { level: 3, ..Default::default() })?;
+
+        let aborted = decompress_file(&stats.output_path);
+        let cleaned_up = !dir.exists() && stats.output_path.exists();
+        let unlimited = RestoreOptions { max_expansion: None, ..Default::default() };
+        let restored = decompress_with(&stats.output_path, true, &unlimited)?;
+
+        std::fs::remove_dir_all(&dir)?;
+        assert!(format!("{:#}", aborted.unwrap_err()).contains("size limit exceeded"));
+        assert!(cleaned_up, "Partial output goes, the archive stays");
+        assert_eq!(restored, 8 * 1024 * 1024 + 1024);
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_restore_respects_the_expansion_limit() -> Result<()> {
+        let path = PathBuf::from("test_bomb.bin");
+        std::fs::write(&path, vec![0u8; 8 * 1024 * 1024])?;
+        let stats = compress_with(&path, &CompressOptions { level: 3, ..Default::default() })?;
+
+        let aborted = decompress_file(&stats.output_path);
+        let cleaned_up = !path.exists() && stats.output_path.exists();
+        let unlimited = RestoreOptions { max_expansion: None, ..Default::default() };
+        let restored = decompress_with(&stats.output_path, true, &unlimited)?;
+
+        std::fs::remove_file(&path)?;
+        assert!(format!("{:#}", aborted.unwrap_err()).contains("size limit exceeded"));
+        assert!(cleaned_up, "Partial output goes, the archive stays");
+        assert_eq!(restored, 8 * 1024 * 1024);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_to_writer_leaves_disk_alone() -> Result<()> {
+        let dir = PathBuf::from("test_stream_dir");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("only.log"), "stream ".repeat(1000))?;
+        let opts = CompressOptions { level: 3, ..Default::default() };
+
+        let mut file_out = Vec::new();
+        let file_size = compress_to_writer(&dir.join("only.log"), &mut file_out, &opts)?;
+        let mut tar_out = Vec::new();
+        compress_to_writer(&dir, &mut tar_out, &opts)?;
+        let untouched = dir.join("only.log").exists() && !output_path_for(&dir, true, &opts).exists();
+        std::fs::remove_dir_all(&dir)?;
+
+        assert!(untouched);
+        assert_eq!(file_size, 7000);
+        assert_eq!(zstd::decode_all(file_out.as_slice())?, "stream ".repeat(1000).into_bytes());
+        // Even a one-file folder is tarred, under its own name
+        let mut archive = tar::Archive::new(zstd::Decoder::new(tar_out.as_slice())?);
+        let names: Vec<PathBuf> = archive.entries()?.map(|e| Ok(e?.path()?.into_owned())).collect::<Result<_>>()?;
+        assert!(names.contains(&PathBuf::from("test_stream_dir/only.log")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_streams_round_trip_through_readers() -> Result<()> {
+        let dir = PathBuf::from("test_stdin_src");
+        std::fs::create_dir_all(dir.join("sub"))?;
+        std::fs::write(dir.join("sub").join("data.log"), "piped ".repeat(1000))?;
+        let opts = CompressOptions { level: 3, ..Default::default() };
+        let mut tar_out = Vec::new();
+        compress_to_writer(&dir, &mut tar_out, &opts)?;
+        let mut file_out = Vec::new();
+        compress_to_writer(&dir.join("sub").join("data.log"), &mut file_out, &opts)?;
+
+        let target = PathBuf::from("test_stdin_out");
+        decompress_reader(tar_out.as_slice(), StreamFormat::TarZst, &target)?;
+        let size = decompress_reader(file_out.as_slice(), StreamFormat::Zst, &target.join("single.log"))?;
+        let unpacked = std::fs::read_to_string(target.join("test_stdin_src/sub/data.log"))?;
+        let single = std::fs::read_to_string(target.join("single.log"))?;
+        let wrong = decompress_reader(file_out.as_slice(), StreamFormat::TarZst, &target.join("wrong"));
+
+        std::fs::remove_dir_all(&dir)?;
+        std::fs::remove_dir_all(&target)?;
+        assert_eq!(unpacked, "piped ".repeat(1000));
+        assert_eq!((size, single.len()), (6000, 6000));
+        assert!(wrong.is_err(), "A plain .zst isn't a tar");
+        assert_eq!(StreamFormat::parse(".tar.zst"), Some(StreamFormat::TarZst));
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_can_keep_the_archive() -> Result<()> {
+        let path = PathBuf::from("test_keep_archive.log");
+        std::fs::write(&path, "kept ".repeat(10_000))?;
+        let stats = compress_with(&path, &CompressOptions { level: 3, ..Default::default() })?;
+
+        let restored = decompress_with(&stats.output_path, true, &RestoreOptions { keep_archive: true, ..Default::default() })?;
+        let archive_kept = stats.output_path.exists() && Sidecar::path_for(&stats.output_path).exists();
+        let content = std::fs::read_to_string(&path)?;
+
+        std::fs::remove_file(&path)?;
+        remove_artifact(&stats.output_path)?;
+        assert!(archive_kept);
+        assert_eq!(restored, 50_000);
+        assert_eq!(content.len(), 50_000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_never_overwrites_the_target() -> Result<()> {
+        let path = PathBuf::from("test_restore_taken.log");
+        std::fs::write(&path, "archived ".repeat(1000))?;
+        let stats = compress_with(&path, &CompressOptions { level: 3, ..Default::default() })?;
+
+        // Something new took the name since
+        std::fs::write(&path, "newer")?;
+        let refused = decompress_file(&stats.output_path);
+        let content = std::fs::read_to_string(&path)?;
+        let archive_kept = stats.output_path.exists();
+
+        std::fs::remove_file(&path)?;
+        remove_artifact(&stats.output_path)?;
+        assert!(format!("{:#}", refused.unwrap_err()).contains("already exists"));
+        assert_eq!(content, "newer");
+        assert!(archive_kept);
+        assert!(!persist::sibling(&path, "tmp").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_stamps_the_recorded_mtime() -> Result<()> {
+        let dir = PathBuf::from("test_restore_mtime_dir");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("a.o"), "object ".repeat(1000))?;
+        std::fs::write(dir.join("b.o"), "object ".repeat(1000))?;
+        let long_ago = SystemTime::now() - Duration::from_secs(90 * 24 * 60 * 60);
+        File::open(&dir)?.set_modified(long_ago)?;
+        let stats = compress_file(&dir, 3)?;
+        let archived_at = Sidecar::read(&stats.output_path).and_then(|s| s.archived_at);
+
+        let keep = RestoreOptions { keep_archive: true, ..Default::default() };
+        decompress_with(&stats.output_path, true, &keep)?;
+        let original = modified_time(&dir);
+        std::fs::remove_dir_all(&dir)?;
+        decompress_with(&stats.output_path, true, &RestoreOptions { match_archive_mtime: true, ..keep })?;
+        let matched = modified_time(&dir);
+
+        std::fs::remove_dir_all(&dir)?;
+        remove_artifact(&stats.output_path)?;
+        assert_eq!(original, Some(long_ago));
+        assert!(archived_at.is_some());
+        assert_eq!(matched, archived_at);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_stage_leaves_the_original() -> Result<()> {
+        let opts = CompressOptions { level: 3, verify: true, ..Default::default() };
+        for stage in [Stage::Compress, Stage::Verify, Stage::Place] {
+            let path = PathBuf::from(format!("test_stage_{:?}.log", stage));
+            std::fs::write(&path, "stage ".repeat(10_000))?;
+            let output = output_path_for(&path, false, &opts);
+
+            FAIL_AT.with(|f| f.set(Some(stage)));
+            let result = compress_with(&path, &opts);
+            FAIL_AT.with(|f| f.set(None));
+
+            let content = std::fs::read_to_string(&path)?;
+            std::fs::remove_file(&path)?;
+            let err = result.err().expect("An injected failure should surface");
+            assert!(err.to_string().contains("original left in place"), "{:?}: {}", stage, err);
+            assert_eq!(content.len(), 60_000);
+            assert!(!output.exists(), "{:?} left an artifact", stage);
+            assert!(!output.with_extension("zst.tmp").exists(), "{:?} left a temp file", stage);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_removal_keeps_both_copies() -> Result<()> {
+        let dir = PathBuf::from("test_stage_remove_dir");
+        std::fs::create_dir_all(dir.join("sub"))?;
+        std::fs::write(dir.join("a.txt"), "alpha ".repeat(5000))?;
+        std::fs::write(dir.join("sub").join("b.txt"), "beta ".repeat(5000))?;
+        let opts = CompressOptions { level: 3, verify: true, ..Default::default() };
+
+        FAIL_AT.with(|f| f.set(Some(Stage::RemoveOriginal)));
+        let result = compress_with(&dir, &opts);
+        FAIL_AT.with(|f| f.set(None));
+
+        let output = output_path_for(&dir, true, &opts);
+        let both = dir.join("a.txt").exists() && output.exists();
+        std::fs::remove_dir_all(&dir)?;
+        let _ = std::fs::remove_file(&output);
+        assert!(result.is_err());
+        assert!(both);
+        Ok(())
+    }
+
+    #[test]
+    fn test_locked_original_rolls_back_the_artifact() -> Result<()> {
+        let path = PathBuf::from("test_stage_locked.log");
+        std::fs::write(&path, "locked ".repeat(5000))?;
+        let opts = CompressOptions { level: 3, ..Default::default() };
+
+        FAIL_AT.with(|f| f.set(Some(Stage::RemoveOriginal)));
+        FAIL_OS_ERROR.with(|f| f.set(Some(if cfg!(windows) { 32 } else { 26 })));
+        let result = compress_with(&path, &opts);
+        FAIL_AT.with(|f| f.set(None));
+        FAIL_OS_ERROR.with(|f| f.set(None));
+
+        let output = output_path_for(&path, false, &opts);
+        let original_kept = path.exists();
+        let artifact_left = output.exists();
+        std::fs::remove_file(&path)?;
+        let _ = std::fs::remove_file(&output);
+        let err = result.err().expect("a locked original fails the compression");
+        assert!(format!("{:#}", err).starts_with(LOCKED_REASON));
+        assert!(original_kept);
+        assert!(!artifact_left);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_catches_a_bad_artifact() -> Result<()> {
+        let path = PathBuf::from("test_verify_mismatch.log");
+        std::fs::write(&path, "verify ".repeat(10_000))?;
+        let stats = compress_with(&path, &CompressOptions { level: 3, verify: true, ..Default::default() })?;
+
+        let mut hashed = Checksum::new(std::io::sink(), true);
+        hashed.write_all("verify ".repeat(10_000).as_bytes())?;
+        let good = verify_artifact(&stats.output_path, hashed.sum().unwrap_or_default());
+        let bad = verify_artifact(&stats.output_path, 42);
+
+        remove_artifact(&stats.output_path)?;
+        assert!(!path.exists());
+        assert!(good.is_ok());
+        assert!(bad.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_moves_original_to_backup() -> Result<()> {
+        // Setup
+        let path = PathBuf::from("test_backup.log");
+        let store = PathBuf::from("test_backup_store");
+        std::fs::write(&path, vec![b'A'; 1024 * 1024])?;
+        let opts = CompressOptions { level: 3, backup_dir: Some(store.clone()), ..Default::default() };
+
+        // Act
+        compress_with(&path, &opts)?;
+
+        // Assert: gone from its place, but recoverable from the store
+        assert!(!path.exists());
+        let backed_up: Vec<_> = walkdir::WalkDir::new(&store).into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name() == "test_backup.log")
+            .collect();
+        assert_eq!(backed_up.len(), 1);
+
+        // Cleanup
+        remove_artifact(&path.with_extension("log.zst"))?;
+        std::fs::remove_dir_all(&store)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_level_policy() {
+        let adaptive = CompressOptions { level: 15, adaptive_levels: true, ..Default::default() };
+        assert_eq!(level_for(Path::new("app.log"), &adaptive), 19);
+        assert_eq!(level_for(Path::new("photo.JPG"), &adaptive), 3);
+        assert_eq!(level_for(Path::new("blob.bin"), &adaptive), 15);
+
+        // Overrides win, and apply without the adaptive policy too
+        let mut fixed = CompressOptions { level: 15, ..Default::default() };
+        fixed.level_overrides.insert("bin".to_string(), 5);
+        assert_eq!(level_for(Path::new("blob.bin"), &fixed), 5);
+        assert_eq!(level_for(Path::new("app.log"), &fixed), 15);
+
+        // The memory guard's ceiling applies to every route
+        assert_eq!(peak_level(&adaptive), 19);
+        let capped = CompressOptions { max_level: 12, ..adaptive };
+        assert_eq!(level_for(Path::new("app.log"), &capped), 12);
+        assert_eq!(peak_level(&capped), 12);
+    }
+
+    #[test]
+    fn test_estimate_best_backend_leaves_input_alone() -> Result<()> {
+        // Setup
+        let path = PathBuf::from("test_estimate_backend.log");
+        std::fs::write(&path, b"GET /index.html 200\n".repeat(200_000))?;
+
+        // Act
+        let (backend, stats) = estimate_best_backend(&path, &CompressOptions { level: 3, ..Default::default() })?;
+
+        // Assert: sampled, capped, and nothing written
+        assert!(Backend::ALL.contains(&backend));
+        assert_eq!(stats.original_size, SAMPLE_BYTES);
+        assert!(stats.compressed_size < stats.original_size / 10);
+        assert!(!path.with_extension("log.zst").exists());
+
+        // Cleanup
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    /// Fails with `kind` the first `failures` reads, then reads from `data`.
+    struct Flaky<'a> {
+        data: &'a [u8],
+        failures: u32,
+        kind: std::io::ErrorKind,
+    }
+
+    impl Read for Flaky<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(self.kind.into());
+            }
+            self.data.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_transient_read_errors_are_retried() -> Result<()> {
+        let flaky = Flaky { data: b"payload", failures: 2, kind: std::io::ErrorKind::TimedOut };
+        let mut reader = Retrying { inner: flaky, retries: 0 };
+
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out)?;
+
+        assert_eq!(out, b"payload");
+        assert_eq!(reader.retries, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_permanent_read_errors_fail_fast() {
+        let flaky = Flaky { data: b"payload", failures: 1, kind: std::io::ErrorKind::PermissionDenied };
+        let mut reader = Retrying { inner: flaky, retries: 0 };
+
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(reader.retries, 0);
+    }
+
+    #[test]
+    fn test_compress_missing_file_is_skipped() -> Result<()> {
+        // Setup: A path the scanner saw but that was deleted since
+        let path = PathBuf::from("test_vanished.log");
+        let _ = std::fs::remove_file(&path);
+
+        // Act
+        let stats = compress_file(&path, 3)?;
+
+        // Assert
+        assert_eq!(stats.skipped.as_deref(), Some(VANISHED_REASON));
+        assert!(!path.with_extension("log.zst").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_fd_exhaustion_gets_actionable_message() {
+        let err = explain_fd_exhaustion(std::io::Error::from_raw_os_error(24).into());
+        assert!(err.to_string().contains("max_open_files"));
+
+        let other = explain_fd_exhaustion(std::io::Error::from_raw_os_error(13).into());
+        assert!(!other.to_string().contains("max_open_files"));
+    }
+
+    #[test]
+    fn test_compress_skips_empty_file() -> Result<()> {
+        let path = PathBuf::from("test_empty.log");
+        File::create(&path)?;
+
+        let stats = compress_file(&path, 3)?;
+
+        assert_eq!(stats.skipped.as_deref(), Some(EMPTY_REASON));
+        assert!(path.exists(), "Empty file should be left alone");
+        assert!(!path.with_extension("log.zst").exists());
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_skips_empty_dir() -> Result<()> {
+        // Only empty subdirectories and zero-byte files: nothing worth archiving
+        let dir = PathBuf::from("test_empty_dir");
+        std::fs::create_dir_all(dir.join("nested"))?;
+        File::create(dir.join("nested/empty.txt"))?;
+
+        let stats = compress_file(&dir, 3)?;
+
+        assert_eq!(stats.skipped.as_deref(), Some(EMPTY_REASON));
+        assert!(dir.exists());
+        assert!(!PathBuf::from("test_empty_dir.tar.zst").exists());
+
+        std::fs::remove_dir_all(&dir)?;
         Ok(())
     }
 
@@ -247,4 +1983,148 @@ mod tests {
         std::fs::remove_file(path)?;
         Ok(())
     }
+
+    #[test]
+    fn test_compress_skips_existing_archive() -> Result<()> {
+        // Setup: Directory next to an archive from a previous run
+        let dir = PathBuf::from("test_existing_archive_dir");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("data.log"), vec![b'A'; 64 * 1024])?;
+        let archive = PathBuf::from("test_existing_archive_dir.tar.zst");
+        std::fs::write(&archive, b"previous run")?;
+
+        // Act
+        let stats = compress_file(&dir, 3)?;
+
+        // Assert
+        assert_eq!(stats.skipped.as_deref(), Some("Already compressed"));
+        assert!(dir.exists(), "Source should be left alone");
+        assert_eq!(std::fs::read(&archive)?, b"previous run", "Existing archive must not be overwritten");
+
+        // Cleanup
+        std::fs::remove_dir_all(&dir)?;
+        std::fs::remove_file(&archive)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_refuses_output_path_taken_by_directory() -> Result<()> {
+        // Setup: a directory squatting on the artifact name
+        let path = PathBuf::from("test_occupied.log");
+        let squatter = PathBuf::from("test_occupied.log.zst");
+        std::fs::write(&path, vec![b'A'; 64 * 1024])?;
+        std::fs::create_dir_all(squatter.join("keep"))?;
+
+        // Act
+        let result = compress_file(&path, 3);
+
+        // Assert: clear error, nothing touched, no temp left behind
+        let err = result.err().expect("Compressing onto a directory should fail");
+        assert!(err.to_string().contains("already taken by a directory"));
+        assert!(path.exists());
+        assert!(squatter.join("keep").is_dir());
+        assert!(!PathBuf::from("test_occupied.log.zst.tmp").exists());
+
+        // Cleanup
+        std::fs::remove_file(&path)?;
+        std::fs::remove_dir_all(&squatter)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_place_artifact_never_clobbers() -> Result<()> {
+        // Setup: something appeared at the output name mid-compression
+        let temp = PathBuf::from("test_place.log.zst.tmp");
+        let output = PathBuf::from("test_place.log.zst");
+        std::fs::write(&temp, b"new")?;
+        std::fs::write(&output, b"someone else's")?;
+
+        // Act
+        let result = place_artifact(&temp, &output);
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&output)?, b"someone else's");
+        assert!(!temp.exists(), "Temp file should be cleaned up");
+
+        // Cleanup
+        std::fs::remove_file(&output)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_moved_archive_to_original_location() -> Result<()> {
+        // Setup: archive a nested folder, then move the archive away and
+        // remove the folder's parent entirely
+        let home = PathBuf::from("test_restore_home");
+        let dir = home.join("proj/cache");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("data.log"), vec![b'A'; 64 * 1024])?;
+        std::fs::write(dir.join("more.log"), vec![b'B'; 64 * 1024])?;
+        let stats = compress_file(&dir, 3)?;
+
+        let moved = PathBuf::from("test_restore_moved.tar.zst");
+        std::fs::rename(&stats.output_path, &moved)?;
+        std::fs::rename(Sidecar::path_for(&stats.output_path), Sidecar::path_for(&moved))?;
+        std::fs::remove_dir_all(&home)?;
+        assert!(Sidecar::read(&moved).is_some_and(|s| s.artifact_moved(&moved)));
+
+        // Act
+        decompress_file(&moved)?;
+
+        // Assert: back where it started, parents recreated
+        assert_eq!(std::fs::read(dir.join("data.log"))?.len(), 64 * 1024);
+        assert!(!moved.exists());
+        assert!(!Sidecar::path_for(&moved).exists());
+
+        // Cleanup
+        std::fs::remove_dir_all(&home)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_file_dir_skips_tar() -> Result<()> {
+        // Setup: a folder holding one big file
+        let dir = PathBuf::from("test_lone_file_dir");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("dump.sql"), vec![b'A'; 64 * 1024])?;
+
+        // Act
+        let stats = compress_file(&dir, 3)?;
+
+        // Assert: a plain .zst named after the folder, no tar wrapper
+        assert_eq!(stats.output_path, PathBuf::from("test_lone_file_dir.zst"));
+        assert!(!dir.exists());
+        assert_eq!(find_artifact(&dir, &CompressOptions::default()), stats.output_path);
+
+        // Restore recreates the folder around the file
+        decompress_file(&stats.output_path)?;
+        assert_eq!(std::fs::read(dir.join("dump.sql"))?.len(), 64 * 1024);
+        assert!(!stats.output_path.exists());
+
+        // Cleanup
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_archive_reads_entries() -> Result<()> {
+        // Setup: Directory with a single compressible file
+        let dir = PathBuf::from("test_list_archive_dir");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("data.log"), vec![b'A'; 64 * 1024])?;
+
+        let stats = compress_file(&dir, 3)?;
+
+        // Act
+        let entries = list_archive(&stats.output_path, &Naming::default())?;
+
+        // Assert
+        assert!(!dir.exists(), "Original directory should be archived");
+        assert!(entries.iter().any(|(name, size)| name.ends_with("data.log") && *size == 64 * 1024));
+
+        // Cleanup
+        remove_artifact(&stats.output_path)?;
+        Ok(())
+    }
 }
@@ -1,21 +1,407 @@
 
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use anyhow::{Result, Context};
 
-#[derive(Debug, Deserialize)]
+use crate::compressor::{self, Backend};
+use crate::naming::{self, Naming};
+use crate::spyder::TimeBasis;
+
+/// Settings from the `--config` TOML file. Every key is optional, so a partial
+/// (or empty) file is valid and missing sections fall back to their defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
 pub struct Config {
-    pub scan: Option<String>,
-    pub compression_level: Option<i32>,
+    pub scan: ScanConfig,
+    pub compress: CompressConfig,
+    pub delete: DeleteConfig,
+    pub restore: RestoreConfig,
+    pub backup: BackupConfig,
+    pub profile: Option<Profile>, // Level, backend and parallelism in one; explicit keys win
+    pub ascii_mode: Option<bool>, // Force ASCII glyphs; default: detected from the locale
+    pub safe_mode: Option<bool>, // Never delete originals; overrides keep_original
+    pub tick_rate_ms: Option<u64>, // UI refresh interval; default 250, clamped to 20..=1000
+    pub list_view: ListView, // Scanner columns; `m` cycles through them
+    pub columns: Option<Vec<String>>, // Own Scanner column set, in order; see Column. Starts in ListView::Custom
+    pub history_path: Option<String>, // Analytics history file; default: history.json in the data directory
+    pub savings_ratios: HashMap<String, f64>, // e.g. node_modules = 0.6, log = 0.9
+
+    /// Unknown or deprecated keys found while loading; shown before the TUI starts.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+/// `[scan]`: what gets crawled.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ScanConfig {
+    pub path: Option<String>,
+    pub exclude: Vec<String>, // Globs never crawled; see spyder::Spyder::crawl
+    pub exclude_extensions: Vec<String>, // Files never listed, e.g. ["sqlite", "db"]
+    pub large_file_mb: Option<u64>, // List any file at least this big; off unless set
+    pub time_basis: TimeBasis, // accessed, modified or created; what "stale" and ages are measured from
+    pub skip_dirs: Option<Vec<String>>, // Folder names never walked; default: spyder::DEFAULT_SKIP_DIRS. .git always is
+}
+
+/// `[compress]`: how candidates get compressed.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CompressConfig {
+    pub level: Option<i32>,
+    pub adaptive_levels: bool, // Higher levels for text, lower for media; see compressor::level_for
+    pub levels: HashMap<String, i32>, // Per-extension levels, e.g. log = 19
+    pub keep_original: Option<bool>, // Leave sources next to their .zst copies
+    pub output_dir: Option<String>, // Mirror artifacts under this directory
+    pub dereference_symlinks: bool, // Archive what links point to, not the links
+    pub protected_paths: Vec<String>, // Never compressed, in addition to compressor::PROTECTED_PATHS
+    pub verify: bool, // Decode and checksum each artifact before removing its original
+    pub protect_recent_minutes: Option<u64>, // Leave anything modified this recently alone; 0 = off. Default 5
+    pub max_jobs: Option<usize>, // Parallel compress/restore jobs; default: all cores
+    pub max_open_files: Option<usize>, // Descriptor budget for those jobs; default: 128
+    pub memory_fraction: Option<f64>, // Share of RAM a batch may use; see memory::fit. Default: 0.5
+    pub file_template: Option<String>, // Artifact names for files; see naming::Naming. Default: "{name}.{ext}.zst"
+    pub dir_template: Option<String>, // Same for folders. Default: "{name}.tar.zst"
+}
+
+impl Default for CompressConfig {
+    fn default() -> Self {
+        CompressConfig {
+            level: None,
+            adaptive_levels: true,
+            levels: HashMap::new(),
+            keep_original: None,
+            output_dir: None,
+            dereference_symlinks: false,
+            protected_paths: Vec::new(),
+            verify: false,
+            protect_recent_minutes: None,
+            max_jobs: None,
+            max_open_files: None,
+            memory_fraction: None,
+            file_template: None,
+            dir_template: None,
+        }
+    }
+}
+
+/// `[delete]`: what `D` does.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DeleteConfig {
+    pub trash_fallback: TrashFallback,
+}
+
+/// `[backup]`: keep removed originals under `<data dir>/backups` for a while.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    pub retention_days: u64, // Sessions older than this are purged at startup
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig { enabled: false, retention_days: 30 }
+    }
+}
+
+/// `[restore]`: what `E` and `U` do with archives.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RestoreConfig {
+    pub keep_archive: bool, // Leave the .zst (and sidecar) after extracting it
+    pub max_expansion: Option<u64>, // Abort a restore past this multiple of the archive's size; 0 = no limit. Default 1000
+    pub match_archive_mtime: bool, // Stamp a restored item with the archive's creation time instead of its original mtime
+}
+
+/// Where deletes go when the OS trash is unavailable (e.g. headless Linux).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashFallback {
+    /// Move into `<data dir>/trash` instead.
+    #[default]
+    PiperTrash,
+    /// Leave the item in place and report the error.
+    None,
+}
+
+/// Named bundles of compression settings, for when a raw level means little.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    /// Low level, every core: quick passes over big trees.
+    Fast,
+    /// The defaults.
+    Balanced,
+    /// Highest practical level with long-distance matching, on half the
+    /// cores since each job then needs a few hundred MB.
+    Max,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Fast => "fast",
+            Profile::Balanced => "balanced",
+            Profile::Max => "max",
+        }
+    }
+
+    pub fn level(self) -> i32 {
+        match self {
+            Profile::Fast => 3,
+            Profile::Balanced => compressor::DEFAULT_LEVEL,
+            Profile::Max => 19,
+        }
+    }
+
+    pub fn backend(self) -> Backend {
+        match self {
+            Profile::Max => Backend::ZstdLong,
+            Profile::Fast | Profile::Balanced => Backend::Zstd,
+        }
+    }
+
+    /// Parallel jobs; `None` for all cores.
+    pub fn max_jobs(self) -> Option<usize> {
+        match self {
+            Profile::Max => std::thread::available_parallelism().ok().map(|n| (n.get() / 2).max(1)),
+            Profile::Fast | Profile::Balanced => None,
+        }
+    }
+}
+
+/// How much the Scanner table shows per row.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListView {
+    /// Icon, path and size, for narrow terminals.
+    Compact,
+    /// Adds the type/reason and ratio columns.
+    #[default]
+    Normal,
+    /// Adds age on top of that.
+    Detailed,
+    /// The `columns` list from the config; `Normal`'s without one.
+    Custom,
 }
 
+impl ListView {
+    /// The next view `m` switches to. `Custom` is only part of the cycle
+    /// when `columns` is set, so the app decides when to step into it.
+    pub fn next(self) -> Self {
+        match self {
+            ListView::Compact => ListView::Normal,
+            ListView::Normal => ListView::Detailed,
+            ListView::Detailed | ListView::Custom => ListView::Compact,
+        }
+    }
+
+    /// The columns a built-in view shows, in order.
+    pub fn columns(self) -> &'static [Column] {
+        match self {
+            ListView::Compact => &[Column::Status, Column::Path, Column::Size],
+            ListView::Normal | ListView::Custom => &[Column::Status, Column::Path, Column::Type, Column::Size, Column::Ratio],
+            ListView::Detailed => &[Column::Status, Column::Path, Column::Type, Column::Size, Column::Ratio, Column::Age],
+        }
+    }
+}
+
+/// One Scanner column, as named in `columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Status, // Icon for the item's state or category
+    Path, // Full path, with the selection checkbox
+    Relative, // Path from its project root (or the scan root), with the checkbox
+    Type, // Why it's listed, or what happened to it
+    Size, // Size, and what it became once processed
+    Ratio, // Compression ratio, once compressed
+    Age, // Days since last touched
+    Files, // Files inside, for folders
+}
+
+impl Column {
+    pub const ALL: [Column; 8] = [
+        Column::Status, Column::Path, Column::Relative, Column::Type,
+        Column::Size, Column::Ratio, Column::Age, Column::Files,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Status => "status",
+            Column::Path => "path",
+            Column::Relative => "relative",
+            Column::Type => "type",
+            Column::Size => "size",
+            Column::Ratio => "ratio",
+            Column::Age => "age",
+            Column::Files => "files",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Column::ALL.into_iter().find(|c| c.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Flat top-level keys from before the `[scan]`/`[compress]` sections, and where they live now.
+const LEGACY_KEYS: &[(&str, &str, &str)] = &[
+    ("scan", "scan", "path"),
+    ("exclude", "scan", "exclude"),
+    ("compression_level", "compress", "level"),
+    ("keep_original", "compress", "keep_original"),
+    ("output_dir", "compress", "output_dir"),
+    ("max_jobs", "compress", "max_jobs"),
+    ("max_open_files", "compress", "max_open_files"),
+];
+
 impl Config {
+    /// `piper/config.toml` in the platform config directory (`$XDG_CONFIG_HOME`,
+    /// i.e. `~/.config` on Linux), read when `--config` isn't given.
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("piper").join("config.toml"))
+    }
+
+    /// `[compress] level` if set, else the profile's, clamped to what zstd accepts.
+    pub fn compression_level(&self) -> i32 {
+        self.compress.level
+            .or(self.profile.map(Profile::level))
+            .unwrap_or(compressor::DEFAULT_LEVEL)
+            .clamp(compressor::MIN_LEVEL, compressor::MAX_LEVEL)
+    }
+
+    /// `[compress] max_jobs` if set, else the profile's.
+    pub fn max_jobs(&self) -> Option<usize> {
+        self.compress.max_jobs.or_else(|| self.profile.and_then(Profile::max_jobs))
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.profile.map_or(Backend::Zstd, Profile::backend)
+    }
+
+    /// Artifact naming from `file_template`/`dir_template`; an error if either is unusable.
+    pub fn naming(&self) -> Result<Naming> {
+        Naming::new(
+            self.compress.file_template.as_deref().unwrap_or(naming::DEFAULT_FILE_TEMPLATE),
+            self.compress.dir_template.as_deref().unwrap_or(naming::DEFAULT_DIR_TEMPLATE),
+        )
+    }
+
+    /// The `columns` that name a known column, in order; `None` when unset
+    /// or when none of them do. Unknown names are warned about by `parse`.
+    pub fn columns(&self) -> Option<Vec<Column>> {
+        let columns: Vec<Column> = self.columns.iter().flatten()
+            .filter_map(|name| Column::parse(name))
+            .collect();
+        (!columns.is_empty()).then_some(columns)
+    }
+
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = fs::read_to_string(path)
             .context("Failed to read config file")?;
-        let config: Config = toml::from_str(&content)
+        Self::parse(&content)
+    }
+
+    /// Parses TOML, moving legacy flat keys into their sections. Unknown keys
+    /// become `warnings` instead of errors so a typo doesn't discard the file.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(content)
             .context("Failed to parse config file")?;
+        let mut warnings = migrate_legacy_keys(&mut table);
+
+        let mut config: Config = serde_ignored::deserialize(toml::Value::Table(table), |key| {
+            warnings.push(format!("unknown config key `{}`", key));
+        })
+        .context("Failed to parse config file")?;
+
+        for name in config.columns.iter().flatten() {
+            if Column::parse(name).is_none() {
+                let known: Vec<_> = Column::ALL.iter().map(|c| c.name()).collect();
+                warnings.push(format!("unknown column `{}` in `columns` (known: {})", name, known.join(", ")));
+            }
+        }
+
+        config.warnings = warnings;
         Ok(config)
     }
 }
+
+fn migrate_legacy_keys(table: &mut toml::Table) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (old, section, new) in LEGACY_KEYS {
+        // `scan` is both a legacy string key and the new section name
+        if table.get(*old).is_none_or(|v| v.is_table()) {
+            continue;
+        }
+        let Some(value) = table.remove(*old) else { continue };
+
+        let target = table
+            .entry(section.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let Some(target) = target.as_table_mut() {
+            target.entry(new.to_string()).or_insert(value);
+        }
+        warnings.push(format!("`{}` is deprecated; use `{}` under [{}]", old, new, section));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_flat_keys_still_load() -> Result<()> {
+        let config = Config::parse("scan = \"/tmp/proj\"\ncompression_level = 5\nexclude = [\"*.db\"]\n")?;
+
+        assert_eq!(config.scan.path.as_deref(), Some("/tmp/proj"));
+        assert_eq!(config.scan.exclude, vec!["*.db".to_string()]);
+        assert_eq!(config.compress.level, Some(5));
+        assert_eq!(config.warnings.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_keys_are_warnings() -> Result<()> {
+        let config = Config::parse("[compress]\nlevel = 9\nlevle = 3\n\n[colour]\ntheme = \"dark\"\n")?;
+
+        assert_eq!(config.compress.level, Some(9));
+        assert!(config.warnings.iter().any(|w| w.contains("compress.levle")));
+        assert!(config.warnings.iter().any(|w| w.contains("colour")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_explicit_level_overrides_profile() -> Result<()> {
+        let config = Config::parse("profile = \"max\"\n")?;
+        assert_eq!(config.compression_level(), 19);
+        assert_eq!(config.backend(), Backend::ZstdLong);
+
+        let config = Config::parse("profile = \"fast\"\n[compress]\nlevel = 12\nmax_jobs = 2\n")?;
+        assert_eq!(config.compression_level(), 12);
+        assert_eq!(config.max_jobs(), Some(2));
+        assert_eq!(Config::parse("")?.compression_level(), compressor::DEFAULT_LEVEL);
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_view_defaults_to_normal() -> Result<()> {
+        assert_eq!(Config::parse("")?.list_view, ListView::Normal);
+        assert_eq!(Config::parse("list_view = \"compact\"\n")?.list_view, ListView::Compact);
+        assert_eq!(ListView::Detailed.next(), ListView::Compact);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_columns_are_dropped_with_a_warning() -> Result<()> {
+        let config = Config::parse("columns = [\"status\", \"Relative\", \"owner\", \"files\"]\n")?;
+        assert_eq!(config.columns(), Some(vec![Column::Status, Column::Relative, Column::Files]));
+        assert!(config.warnings.iter().any(|w| w.contains("`owner`")));
+
+        assert_eq!(Config::parse("columns = [\"owner\"]\n")?.columns(), None);
+        assert_eq!(Config::parse("")?.columns(), None);
+        Ok(())
+    }
+}
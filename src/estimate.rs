@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Rough "how much will compression save" guesses, used before any CPU is spent.
+/// Ratios are the fraction of the original size expected to be reclaimed,
+/// keyed by heavy-dir name (`node_modules`) or file extension (`log`).
+/// The `default` key covers anything else.
+#[derive(Debug, Clone)]
+pub struct SavingsEstimator {
+    ratios: HashMap<String, f64>,
+}
+
+impl Default for SavingsEstimator {
+    fn default() -> Self {
+        let ratios = [
+            ("node_modules", 0.6),
+            ("target", 0.7),
+            ("venv", 0.5),
+            (".venv", 0.5),
+            ("log", 0.9),
+            ("txt", 0.8),
+            ("old", 0.85),
+            ("default", 0.5),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        Self { ratios }
+    }
+}
+
+impl SavingsEstimator {
+    /// Built-in ratios with user-provided ones layered on top.
+    pub fn with_overrides(overrides: &HashMap<String, f64>) -> Self {
+        let mut estimator = Self::default();
+        for (key, ratio) in overrides {
+            estimator.ratios.insert(key.clone(), ratio.clamp(0.0, 1.0));
+        }
+        estimator
+    }
+
+    pub fn ratio_for(&self, path: &Path) -> f64 {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+
+        self.ratios.get(name.as_ref())
+            .or_else(|| self.ratios.get(ext.as_ref()))
+            .or_else(|| self.ratios.get("default"))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Estimated bytes reclaimed by compressing `path` of `size` bytes.
+    pub fn estimate(&self, path: &Path, size: u64) -> u64 {
+        (size as f64 * self.ratio_for(path)) as u64
+    }
+}
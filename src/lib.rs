@@ -0,0 +1,28 @@
+//! Piper's engine, usable without the TUI; `--no-default-features` leaves
+//! the TUI's dependencies out of the build too.
+//!
+//! - [`spyder`] finds compressible artifacts.
+//! - [`compressor`] compresses and restores them.
+//! - [`naming`] maps sources to artifact names and back.
+//! - [`backup`] keeps removed originals in Piper's data directory (backup store, trash fallback).
+//! - [`sidecar`] remembers where an artifact's source lived.
+//! - [`estimate`] guesses savings before compressing.
+//! - [`memory`] keeps parallel high-level jobs within the machine's RAM.
+//! - [`analytics`] keeps a history of compression runs.
+//! - [`recent`] remembers recently scanned roots.
+//! - [`persist`] writes those files so a crash can't corrupt them.
+//! - [`config`] loads the TOML configuration.
+//! - [`units`] formats sizes for output.
+
+pub mod analytics;
+pub mod backup;
+pub mod compressor;
+pub mod config;
+pub mod estimate;
+pub mod memory;
+pub mod naming;
+pub mod persist;
+pub mod recent;
+pub mod sidecar;
+pub mod spyder;
+pub mod units;
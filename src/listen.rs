@@ -0,0 +1,219 @@
+//! `--listen`: a tiny HTTP endpoint so CI can run scan + compress without a PTY.
+//!
+//! `POST /run` with `{"scan": "/path", "level": 15, "include": ["**/*.log"]}`
+//! scans, compresses the matches and answers with a JSON summary. Every field
+//! is optional; `include` empty means "everything the scan found".
+
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use ignore::overrides::OverrideBuilder;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::app::{self, App};
+use crate::compressor::{self, CompressOptions};
+use crate::memory;
+use crate::spyder::{Spyder, TimeBasis};
+
+/// Defaults for requests that leave fields out, taken from the CLI/config.
+pub struct Defaults {
+    pub scan: PathBuf,
+    pub opts: CompressOptions,
+    pub exclude: Vec<String>,
+    pub exclude_extensions: Vec<String>,
+    pub skip_dirs: Vec<String>,
+    pub big_file_threshold: Option<u64>,
+    pub time_basis: TimeBasis,
+    pub max_jobs: Option<usize>,
+    pub max_open_files: usize,
+    pub total_memory: Option<u64>,
+    pub memory_fraction: f64,
+}
+
+impl Defaults {
+    pub fn from_app(app: &App) -> Self {
+        Defaults {
+            scan: app.scan_path.clone(),
+            opts: app.compress_options(),
+            exclude: app.exclude.clone(),
+            exclude_extensions: app.exclude_extensions.clone(),
+            skip_dirs: app.skip_dirs.clone(),
+            big_file_threshold: app.big_file_threshold,
+            time_basis: app.time_basis,
+            max_jobs: app.max_jobs,
+            max_open_files: app.max_open_files,
+            total_memory: app.total_memory,
+            memory_fraction: app.memory_fraction,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RunRequest {
+    scan: Option<PathBuf>,
+    level: Option<i32>,
+    include: Vec<String>, // Globs relative to the scan root
+}
+
+#[derive(Debug, Default, Serialize)]
+struct RunSummary {
+    scanned: usize,
+    compressed: usize,
+    skipped: usize,
+    failed: usize,
+    original_bytes: u64,
+    compressed_bytes: u64,
+    items: Vec<ItemResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct ItemResult {
+    path: PathBuf,
+    status: &'static str, // "compressed", "skipped" or "failed"
+    original_bytes: u64,
+    compressed_bytes: Option<u64>,
+    reason: Option<String>,
+}
+
+/// Serves until the process is killed. A bare port binds to localhost only.
+pub fn serve(addr: &str, defaults: &Defaults) -> Result<()> {
+    let addr = if addr.contains(':') { addr.to_string() } else { format!("127.0.0.1:{}", addr) };
+    let server = Server::http(&addr).map_err(|e| anyhow!("Failed to listen on {}: {}", addr, e))?;
+    eprintln!("Listening on http://{} (POST /run)", addr);
+
+    for mut request in server.incoming_requests() {
+        let (code, body) = match (request.method(), request.url()) {
+            (Method::Post, "/run") => {
+                let mut body = String::new();
+                let parsed = request.as_reader().read_to_string(&mut body)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|_| parse_request(&body));
+                match parsed.and_then(|req| run(req, defaults)) {
+                    Ok(summary) => (200, serde_json::to_string(&summary)?),
+                    Err(e) => (400, error_json(&format!("{:#}", e))),
+                }
+            }
+            _ => (404, error_json("Not found; use POST /run")),
+        };
+
+        let json = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+            .map_err(|_| anyhow!("Invalid header"))?;
+        let _ = request.respond(Response::from_string(body).with_status_code(code).with_header(json));
+    }
+    Ok(())
+}
+
+fn parse_request(body: &str) -> Result<RunRequest> {
+    if body.trim().is_empty() {
+        return Ok(RunRequest::default());
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn run(req: RunRequest, defaults: &Defaults) -> Result<RunSummary> {
+    let root = req.scan.unwrap_or_else(|| defaults.scan.clone());
+    if !root.is_dir() {
+        return Err(anyhow!("Scan path is not a directory: {}", root.display()));
+    }
+
+    let mut opts = defaults.opts.clone();
+    opts.root = Some(root.clone());
+    if let Some(level) = req.level {
+        opts.level = level.clamp(compressor::MIN_LEVEL, compressor::MAX_LEVEL);
+    }
+
+    let include = include_matcher(&root, &req.include)?;
+    let report = Spyder::new(&root)
+        .exclude(defaults.exclude.clone())
+        .exclude_extensions(defaults.exclude_extensions.clone())
+        .skip_dirs(defaults.skip_dirs.clone())
+        .big_files(defaults.big_file_threshold)
+        .time_basis(defaults.time_basis)
+        .naming(defaults.opts.naming.clone())
+        .protect_recent(defaults.opts.protect_recent)
+        .crawl();
+    let scanned = report.items.len();
+    let targets: Vec<_> = report.items.into_iter()
+        .filter(|item| include.as_ref().is_none_or(|m| m(&item.path)))
+        .collect();
+
+    // The same limits as a batch in the app: max_jobs, open files, and RAM
+    // for the level this request asked for
+    let mut jobs = app::pool_size(defaults.max_jobs, defaults.max_open_files);
+    if let Some(total) = defaults.total_memory {
+        let fit = memory::fit_options(jobs, &opts, total, defaults.memory_fraction);
+        if fit.max_level < compressor::peak_level(&opts) {
+            opts.max_level = fit.max_level;
+        }
+        jobs = fit.jobs;
+    }
+    let pool = app::build_pool(Some(jobs), defaults.max_open_files);
+
+    let items: Vec<ItemResult> = pool.install(|| targets.par_iter()
+        .map(|item| match compressor::compress_with(&item.path, &opts) {
+            Ok(stats) if stats.skipped.is_some() => ItemResult {
+                path: item.path.clone(),
+                status: "skipped",
+                original_bytes: item.size,
+                compressed_bytes: None,
+                reason: stats.skipped,
+            },
+            // The artifact was discarded and the original left alone
+            Ok(stats) if stats.compressed_size >= stats.original_size => ItemResult {
+                path: item.path.clone(),
+                status: "skipped",
+                original_bytes: stats.original_size,
+                compressed_bytes: None,
+                reason: Some("No savings or size increased".to_string()),
+            },
+            Ok(stats) => ItemResult {
+                path: item.path.clone(),
+                status: "compressed",
+                original_bytes: stats.original_size,
+                compressed_bytes: Some(stats.compressed_size),
+                reason: None,
+            },
+            Err(e) => ItemResult {
+                path: item.path.clone(),
+                status: "failed",
+                original_bytes: item.size,
+                compressed_bytes: None,
+                reason: Some(e.to_string()),
+            },
+        })
+        .collect());
+
+    let mut summary = RunSummary { scanned, ..Default::default() };
+    for item in &items {
+        match item.status {
+            "compressed" => {
+                summary.compressed += 1;
+                summary.original_bytes += item.original_bytes;
+                summary.compressed_bytes += item.compressed_bytes.unwrap_or(0);
+            }
+            "skipped" => summary.skipped += 1,
+            _ => summary.failed += 1,
+        }
+    }
+    summary.items = items;
+    Ok(summary)
+}
+
+/// `None` when there are no globs, i.e. everything matches.
+fn include_matcher(root: &Path, globs: &[String]) -> Result<Option<impl Fn(&Path) -> bool>> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = OverrideBuilder::new(root);
+    for glob in globs {
+        builder.add(glob)?;
+    }
+    let overrides = builder.build()?;
+    Ok(Some(move |path: &Path| overrides.matched(path, path.is_dir()).is_whitelist()))
+}
@@ -1,52 +1,210 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::{io, time::Duration};
 use std::path::PathBuf;
+use std::sync::LazyLock;
 use clap::Parser;
 
+#[cfg(feature = "tui")]
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+#[cfg(feature = "tui")]
 use ratatui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
 
 mod app;
-mod compressor;
-
+#[cfg(feature = "listen")]
+mod listen;
+#[cfg(feature = "tui")]
 mod ui;
-mod config;
-mod spyder;
+mod worker;
+
+use piper::{analytics, backup, compressor, config, estimate, memory, naming, recent, spyder, units};
+// Only the TUI's restore reads sidecars from the app
+#[cfg(feature = "tui")]
+use piper::sidecar;
 
 use app::App;
 use config::Config;
 
+/// Multi-line `--version` output; handy to paste into bug reports.
+static LONG_VERSION: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "{}\nzstd:     {}\nbackends: {}\ntarget:   {}\nprofile:  {}",
+        env!("CARGO_PKG_VERSION"),
+        zstd::zstd_safe::version_string(),
+        compressor::Backend::ALL.map(|b| b.name()).join(", "),
+        env!("PIPER_TARGET"),
+        env!("PIPER_PROFILE"),
+    )
+});
+
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, long_version = LONG_VERSION.as_str(), about, long_about = None)]
 struct Args {
     /// Path to scan for optimization (default: ~/Developer)
     #[arg(short, long)]
     scan: Option<String>,
 
-    /// Path to configuration file
+    /// Path to configuration file (default: piper/config.toml in the platform config directory, if present)
     #[arg(short, long)]
     config: Option<String>,
+
+    /// Write compressed artifacts under this directory, mirroring the scan tree
+    #[arg(long)]
+    output_dir: Option<String>,
+
+    /// Keep originals next to their compressed copies
+    #[arg(long)]
+    keep_original: bool,
+
+    /// Never remove originals: always keep them and disable delete
+    #[arg(long)]
+    safe: bool,
+
+    /// Never list files with this extension (repeatable), e.g. --exclude-ext sqlite
+    #[arg(long = "exclude-ext", value_name = "EXT")]
+    exclude_ext: Vec<String>,
+
+    /// Use ASCII spinner and status markers (for terminals without UTF-8)
+    #[arg(long)]
+    ascii: bool,
+
+    /// List the sessions in the backup store (<data dir>/backups) and exit
+    #[arg(long)]
+    list_backups: bool,
+
+    /// Permanently delete everything in the backup store and exit
+    #[arg(long)]
+    empty_backups: bool,
+
+    /// Check the data directory, trash, scan path, terminal and zstd, print a report and exit; exits 1 if any check failed
+    #[arg(long, conflicts_with_all = ["scan_only", "top", "stdout", "decompress"])]
+    doctor: bool,
+
+    /// List what a scan would flag (largest first) and exit; exits 2 if nothing was found
+    #[arg(long)]
+    scan_only: bool,
+
+    /// Work on the newline-separated paths in FILE ("-" for stdin) instead of scanning; nonexistent ones are skipped
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["stdout", "decompress", "doctor"])]
+    paths_from: Option<String>,
+
+    /// With --scan-only, print the candidates as JSON
+    #[arg(long, requires = "scan_only")]
+    json: bool,
+
+    /// Compress PATH (a file, or a folder as .tar.zst) to standard output and exit; nothing on disk changes
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["scan_only", "top"])]
+    stdout: Option<PathBuf>,
+
+    /// Restore a stream read from stdin into --output and exit (needs --stdin and --format)
+    #[arg(long, requires_all = ["stdin", "format", "output"], conflicts_with_all = ["scan_only", "top", "stdout"])]
+    decompress: bool,
+
+    /// With --decompress, read the archive from standard input
+    #[arg(long, requires = "decompress")]
+    stdin: bool,
+
+    /// With --stdin, what the stream holds: "zst" (one file) or "tar.zst" (a folder); a pipe can't be sniffed
+    #[arg(long, requires = "stdin", value_parser = ["zst", "tar.zst"])]
+    format: Option<String>,
+
+    /// With --decompress: the file to write (zst), or the folder to unpack into (tar.zst)
+    #[arg(short, long, value_name = "PATH", requires = "decompress")]
+    output: Option<PathBuf>,
+
+    /// Scan, compress the N largest candidates without the TUI, and exit; exits 1 if any failed, 2 if none were found
+    #[arg(long, value_name = "N", conflicts_with = "scan_only")]
+    top: Option<usize>,
+
+    /// With --top, only show what would be compressed and record the projection on the Analytics tab
+    #[arg(long, requires = "top")]
+    dry_run: bool,
+
+    /// Serve scan + compress over HTTP at ADDR (a bare port binds to localhost)
+    #[cfg(feature = "listen")]
+    #[arg(long, value_name = "ADDR")]
+    listen: Option<String>,
+}
+
+/// Exit status contract for scripts; see "Exit codes" in the README.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// Everything asked for was done.
+    Success = 0,
+    /// Some items failed; the rest were processed.
+    Partial = 1,
+    /// The scan found no candidates.
+    NothingToDo = 2,
+    /// Bad arguments, or an error that stopped the run.
+    Fatal = 3,
+}
+
+impl Outcome {
+    /// How a session that processed `app.items` went.
+    fn of(app: &App) -> Self {
+        if app.items.iter().any(|i| i.status == app::FileStatus::Error) {
+            Outcome::Partial
+        } else if app.has_scanned && app.items.is_empty() {
+            Outcome::NothingToDo
+        } else {
+            Outcome::Success
+        }
+    }
 }
 
-fn main() -> Result<()> {
+fn main() {
     tracing_subscriber::fmt::init();
-    let args = Args::parse();
+    // clap exits with 2 on usage errors, which here means "nothing to do"
+    let args = Args::try_parse().unwrap_or_else(|e| {
+        if !e.use_stderr() {
+            e.exit(); // --help / --version
+        }
+        let _ = e.print();
+        std::process::exit(Outcome::Fatal as i32);
+    });
 
-    let config = if let Some(config_path) = &args.config {
-        Config::load_from_file(config_path).ok()
-    } else {
-        None
+    let outcome = run(args).unwrap_or_else(|e| {
+        eprintln!("Error: {:#}", e);
+        Outcome::Fatal
+    });
+    std::process::exit(outcome as i32);
+}
+
+fn run(args: Args) -> Result<Outcome> {
+
+    match backup::migrate_legacy_data_dir() {
+        Ok(Some(dir)) => eprintln!("Note: moved ~/.piper to {}", dir.display()),
+        Ok(None) => {}
+        Err(e) => eprintln!("Warning: could not move ~/.piper: {:#}; still using it", e),
+    }
+
+    // Without --config, pick up the platform config file if there is one
+    let config_path = args.config.clone().map(PathBuf::from)
+        .or_else(|| Config::default_path().filter(|path| path.is_file()));
+    let config = match &config_path {
+        Some(config_path) => Config::load_from_file(config_path).unwrap_or_else(|e| {
+            eprintln!("Warning: {:#}; using defaults", e);
+            Config::default()
+        }),
+        None => Config::default(),
     };
+    for warning in &config.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    if args.list_backups || args.empty_backups {
+        manage_backups(args.empty_backups)?;
+        return Ok(Outcome::Success);
+    }
 
     let scan_path = args.scan
-        .or_else(|| config.as_ref().and_then(|c| c.scan.clone()))
+        .or_else(|| config.scan.path.clone())
         .map(PathBuf::from)
         .unwrap_or_else(|| {
             // Default: ~/Developer
@@ -59,10 +217,132 @@ fn main() -> Result<()> {
             }
         });
 
-    let compression_level = config.as_ref()
-        .and_then(|c| c.compression_level)
-        .unwrap_or(15); // Default Middle-Out Level
+    let compression_level = config.compression_level();
+
+    for glob in spyder::invalid_globs(&config.scan.exclude) {
+        eprintln!("Warning: ignoring invalid exclude glob {:?}", glob);
+    }
 
+    // Create app with path
+    let mut app = App::new(scan_path, compression_level);
+    app.history = analytics::AnalyticsHistory::load_from(
+        config.history_path.as_ref().map(PathBuf::from).or_else(analytics::AnalyticsHistory::default_path),
+    );
+    app.recent = recent::RecentScans::load_from(recent::RecentScans::default_path());
+    app.adaptive_levels = config.compress.adaptive_levels;
+    app.level_overrides = config.compress.levels.iter()
+        .map(|(ext, level)| (ext.trim_start_matches('.').to_lowercase(), *level))
+        .collect();
+    app.max_jobs = config.max_jobs();
+    app.profile = config.profile;
+    app.backend = config.backend();
+    if let Some(limit) = config.compress.max_open_files {
+        app.max_open_files = limit;
+    }
+    if let Some(fraction) = config.compress.memory_fraction {
+        app.memory_fraction = fraction.clamp(0.05, 1.0);
+    }
+    app.output_dir = args.output_dir.clone()
+        .or_else(|| config.compress.output_dir.clone())
+        .map(PathBuf::from);
+    app.keep_original = args.keep_original || config.compress.keep_original.unwrap_or(false);
+    app.dereference_symlinks = config.compress.dereference_symlinks;
+    app.verify = config.compress.verify;
+    app.protect_recent = match config.compress.protect_recent_minutes.unwrap_or(compressor::DEFAULT_PROTECT_RECENT_MINUTES) {
+        0 => None,
+        minutes => Some(Duration::from_secs(minutes * 60)),
+    };
+    app.protected_paths.extend(config.compress.protected_paths.iter().map(|p| compressor::expand_home(p)));
+    if app.dereference_symlinks {
+        eprintln!("Note: dereference_symlinks is on; linked content is copied into archives, which can make them much larger");
+    }
+    app.trash_fallback = config.delete.trash_fallback;
+    app.keep_archive = config.restore.keep_archive;
+    app.max_expansion = match config.restore.max_expansion {
+        Some(0) => None,
+        Some(ratio) => Some(ratio),
+        None => Some(compressor::DEFAULT_MAX_EXPANSION),
+    };
+    app.match_archive_mtime = config.restore.match_archive_mtime;
+    if config.backup.enabled && let Some(store) = backup::backups_dir() {
+        let retention = Duration::from_secs(config.backup.retention_days * 24 * 60 * 60);
+        if let Err(e) = backup::prune(&store, retention) {
+            eprintln!("Warning: could not prune old backups: {:#}", e);
+        }
+        app.backup_dir = Some(backup::session_dir(&store));
+    }
+    app.list_view = config.list_view;
+    if let Some(columns) = config.columns() {
+        app.columns = columns;
+        app.list_view = config::ListView::Custom;
+    }
+    if let Some(ms) = config.tick_rate_ms {
+        app.set_tick_rate_ms(ms);
+    }
+    app.safe_mode = args.safe || config.safe_mode.unwrap_or(false);
+    app.ascii_mode = args.ascii || config.ascii_mode.unwrap_or_else(|| !terminal_supports_unicode());
+    app.exclude = config.scan.exclude.clone();
+    app.big_file_threshold = config.scan.large_file_mb.map(|mb| mb * 1024 * 1024);
+    app.time_basis = config.scan.time_basis;
+    app.naming = config.naming().unwrap_or_else(|e| {
+        eprintln!("Warning: {:#}; using the default artifact names", e);
+        naming::Naming::default()
+    });
+    if let Some(skip_dirs) = &config.scan.skip_dirs {
+        app.skip_dirs = skip_dirs.clone();
+    }
+    app.exclude_extensions = config.scan.exclude_extensions.iter()
+        .chain(&args.exclude_ext)
+        .cloned()
+        .collect();
+    if !config.savings_ratios.is_empty() {
+        app.estimator = estimate::SavingsEstimator::with_overrides(&config.savings_ratios);
+    }
+
+    if args.doctor {
+        return Ok(doctor(&app));
+    }
+
+    // Measured up front, standing in for the scan in every mode below
+    let listed = match &args.paths_from {
+        Some(source) => Some(spyder::measure_paths(read_paths(source)?, app.time_basis)),
+        None => None,
+    };
+
+    if args.scan_only {
+        return scan_only(&app, listed, args.json);
+    }
+
+    if args.decompress
+        && let (Some(format), Some(output)) = (&args.format, &args.output)
+    {
+        return decompress_stdin(format, output);
+    }
+
+    if let Some(path) = &args.stdout {
+        return compress_to_stdout(path, &app.compress_options());
+    }
+
+    if let Some(report) = listed {
+        app.load_listed(report);
+    }
+
+    if let Some(n) = args.top {
+        return compress_top(&mut app, n, args.dry_run);
+    }
+
+    #[cfg(feature = "listen")]
+    if let Some(addr) = &args.listen {
+        listen::serve(addr, &listen::Defaults::from_app(&app))?;
+        return Ok(Outcome::Success);
+    }
+
+    run_tui(&mut app)
+}
+
+/// The interactive session, until `q`.
+#[cfg(feature = "tui")]
+fn run_tui(app: &mut App) -> Result<Outcome> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -70,11 +350,8 @@ fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app with path
-    let mut app = App::new(scan_path, compression_level);
-
     // Run app
-    let res = run_app(&mut terminal, &mut app);
+    let res = run_app(&mut terminal, app);
 
     // Restore terminal
     disable_raw_mode()?;
@@ -85,31 +362,338 @@ fn main() -> Result<()> {
     )?;
     terminal.show_cursor()?;
 
-    if let Err(err) = res {
-        println!("{:?}", err);
+    // Workers are plain threads: returning from main would kill them mid-item
+    if app.has_running_workers() {
+        eprintln!("Waiting for in-flight jobs to finish (Ctrl-C to quit now)...");
+    }
+    if !app.shutdown(app::SHUTDOWN_TIMEOUT) {
+        eprintln!("Warning: gave up on jobs still running after {}s; an interrupted compression keeps its \
+            original (a .tmp may be left next to it), an interrupted restore may be incomplete", app::SHUTDOWN_TIMEOUT.as_secs());
+    }
+
+    res?;
+    Ok(Outcome::of(app))
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_app: &mut App) -> Result<Outcome> {
+    anyhow::bail!("This build has no TUI (the `tui` feature is off); use --scan-only, --top, --stdout or --decompress");
+}
+
+/// `--list-backups` / `--empty-backups`: plain output, no TUI.
+fn manage_backups(empty: bool) -> Result<()> {
+    let Some(store) = backup::backups_dir() else {
+        anyhow::bail!("No home directory, so there is no backup store");
+    };
+
+    if empty {
+        let freed = backup::empty(&store)?;
+        println!("Emptied {} ({} freed)", store.display(), units::format_size(freed));
+        return Ok(());
     }
 
+    let sessions = backup::list(&store)?;
+    if sessions.is_empty() {
+        println!("No backups in {}", store.display());
+    }
+    for session in sessions {
+        println!("{}  {}", session.path.display(), units::format_size(session.size));
+    }
     Ok(())
 }
 
+/// Paths from `--paths-from`, one per line; blank lines are ignored.
+fn read_paths(source: &str) -> Result<Vec<PathBuf>> {
+    let content = if source == "-" {
+        io::read_to_string(io::stdin().lock()).context("Failed to read paths from stdin")?
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("Failed to read {}", source))?
+    };
+    Ok(content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// `--scan-only`: a read-only crawl printed to stdout, or the `--paths-from` list as measured.
+fn scan_only(app: &App, listed: Option<spyder::ScanReport>, json: bool) -> Result<Outcome> {
+    let mut report = match listed {
+        Some(report) => report,
+        None => crawl(app)?,
+    };
+    report.items.sort_by_key(|item| std::cmp::Reverse(item.size));
+    for warning in report.skipped_summary() {
+        eprintln!("Warning: {}", warning);
+    }
+
+    if json {
+        let items: Vec<_> = report.items.iter()
+            .map(|item| serde_json::json!({
+                "path": item.path,
+                "size": item.size,
+                "category": item.category.label(),
+                "reason": item.reason(),
+            }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&items)?);
+    } else {
+        for item in &report.items {
+            println!("{:>10}  {}  ({})", units::format_size(item.size), item.path.display(), item.reason());
+        }
+    }
+    Ok(if report.items.is_empty() { Outcome::NothingToDo } else { Outcome::Success })
+}
+
+/// The crawl `--scan-only` reports.
+fn crawl(app: &App) -> Result<spyder::ScanReport> {
+    if let Some(err) = &app.scan_error {
+        anyhow::bail!("{}", err);
+    }
+    if app::is_dangerous_root(&app.scan_path) {
+        eprintln!("Warning: {} is a very broad scan root; this may take a while", app.scan_path.display());
+    }
+
+    Ok(spyder::Spyder::new(&app.scan_path)
+        .exclude(app.exclude.clone())
+        .exclude_extensions(app.exclude_extensions.clone())
+        .skip_dirs(app.skip_dirs.clone())
+        .big_files(app.big_file_threshold)
+        .time_basis(app.time_basis)
+        .naming(app.naming.clone())
+        .protect_recent(app.protect_recent)
+        .crawl())
+}
+
+/// `--stdout PATH`: streams the artifact for PATH, e.g. into `ssh` or `aws s3 cp -`.
+fn compress_to_stdout(path: &std::path::Path, opts: &compressor::CompressOptions) -> Result<Outcome> {
+    use std::io::{IsTerminal, Write};
+
+    let stdout = io::stdout();
+    if stdout.is_terminal() {
+        anyhow::bail!("Refusing to write compressed data to a terminal; pipe or redirect --stdout");
+    }
+    let mut out = io::BufWriter::new(stdout.lock());
+    compressor::compress_to_writer(path, &mut out, opts)
+        .with_context(|| format!("Failed to compress {}", path.display()))?;
+    out.flush()?;
+    Ok(Outcome::Success)
+}
+
+/// `--decompress --stdin`: the counterpart of `--stdout`.
+fn decompress_stdin(format: &str, output: &std::path::Path) -> Result<Outcome> {
+    let format = compressor::StreamFormat::parse(format)
+        .with_context(|| format!("Unknown format {:?}", format))?;
+    let size = compressor::decompress_reader(io::stdin().lock(), format, output)
+        .with_context(|| format!("Failed to restore into {}", output.display()))?;
+    eprintln!("Restored {} into {}", units::format_size(size), output.display());
+    Ok(Outcome::Success)
+}
+
+/// `--top N`: compresses the largest candidates and prints what happened to each.
+fn compress_top(app: &mut App, n: usize, dry_run: bool) -> Result<Outcome> {
+    // Nothing to check about the root when `--paths-from` stands in for the scan
+    if !app.has_scanned {
+        if let Some(err) = &app.scan_error {
+            anyhow::bail!("{}", err);
+        }
+        if app::is_dangerous_root(&app.scan_path) {
+            eprintln!("Warning: {} is a very broad scan root; this may take a while", app.scan_path.display());
+        }
+    }
+
+    app.compress_top(n, dry_run);
+    for warning in &app.scan_warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Some(notice) = &app.notice {
+        eprintln!("Warning: {}", notice);
+    }
+
+    if dry_run {
+        for item in app.items.iter().filter(|i| i.selected) {
+            println!("{}  {}  (~{} saved)", item.path.display(), units::format_size(item.original_size),
+                units::format_size(app.estimator.estimate(&item.path, item.original_size)));
+        }
+        let (count, _, projected) = app.compression_plan();
+        println!("Would compress {} item{}, saving ~{}", count, if count == 1 { "" } else { "s" }, units::format_size(projected));
+        return Ok(Outcome::of(app));
+    }
+
+    for item in app.items.iter().filter(|i| i.selected) {
+        let outcome = match item.status {
+            app::FileStatus::Done => format!(
+                "{} -> {}",
+                units::format_size(item.original_size),
+                units::format_size(item.compressed_size.unwrap_or_default()),
+            ),
+            app::FileStatus::Error => format!("failed: {}", item.reason),
+            _ => format!("skipped: {}", item.reason),
+        };
+        println!("{}  {}", item.path.display(), outcome);
+    }
+    if !app.items.iter().any(|i| i.selected) {
+        println!("Nothing to compress in {}", app.scan_path.display());
+    }
+    println!("Saved {}", units::format_size(app.total_savings));
+    Ok(Outcome::of(app))
+}
+
+/// `--doctor`: the first-run problems that otherwise only show up as a
+/// failed action, each checked without touching anything of the user's.
+fn doctor(app: &App) -> Outcome {
+    let checks: [(&str, Result<String>); 5] = [
+        ("Data directory", check_data_dir()),
+        ("Trash", check_trash(app.trash_fallback)),
+        ("Scan path", match &app.scan_error {
+            Some(err) => Err(anyhow::anyhow!("{}", err)),
+            None => Ok(app.scan_path.display().to_string()),
+        }),
+        ("Terminal", check_terminal()),
+        ("zstd", check_zstd(&app.compress_options())),
+    ];
+
+    let mut failed = 0;
+    for (name, result) in &checks {
+        match result {
+            Ok(detail) => println!("[ok]   {:<15} {}", name, detail),
+            Err(e) => {
+                failed += 1;
+                println!("[FAIL] {:<15} {:#}", name, e);
+            }
+        }
+    }
+    if failed == 0 {
+        println!("All checks passed");
+        Outcome::Success
+    } else {
+        println!("{} of {} checks failed", failed, checks.len());
+        Outcome::Partial
+    }
+}
+
+/// Writes and removes a probe file where history, backups and the fallback trash live.
+fn check_data_dir() -> Result<String> {
+    let dir = backup::data_dir().context("No home directory; set PIPER_DATA_DIR")?;
+    std::fs::create_dir_all(&dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    let probe = dir.join(format!(".doctor-{}", std::process::id()));
+    std::fs::write(&probe, b"piper").with_context(|| format!("{} is not writable", dir.display()))?;
+    std::fs::remove_file(&probe)?;
+    Ok(format!("{} is writable", dir.display()))
+}
+
+/// Reads the OS trash rather than putting anything in it.
+fn check_trash(fallback: config::TrashFallback) -> Result<String> {
+    let available: Result<String> = {
+        #[cfg(any(windows, all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+        {
+            trash::os_limited::list()
+                .map(|items| format!("available ({} item(s) in it)", items.len()))
+                .map_err(|e| anyhow::anyhow!("{}", e))
+        }
+        #[cfg(not(any(windows, all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+        {
+            let dir = dirs::home_dir().unwrap_or_default().join(".Trash");
+            if dir.is_dir() {
+                Ok(format!("available ({})", dir.display()))
+            } else {
+                Err(anyhow::anyhow!("{} not found", dir.display()))
+            }
+        }
+    };
+    available.map_err(|e| match (fallback, backup::trash_dir()) {
+        (config::TrashFallback::PiperTrash, Some(dir)) => e.context(format!("OS trash unavailable; deletes will go to {}", dir.display())),
+        _ => e.context("OS trash unavailable and trash_fallback = \"none\"; deletes will fail"),
+    })
+}
+
+#[cfg(feature = "tui")]
+fn check_terminal() -> Result<String> {
+    use std::io::IsTerminal;
+
+    if !io::stdout().is_terminal() {
+        anyhow::bail!("stdout is not a terminal; the TUI needs one (headless flags still work)");
+    }
+    let (cols, rows) = crossterm::terminal::size().context("Cannot read the terminal size")?;
+    let glyphs = if terminal_supports_unicode() { "Unicode" } else { "ASCII (no UTF-8 locale)" };
+    Ok(format!("{}x{}, {} glyphs", cols, rows, glyphs))
+}
+
+#[cfg(not(feature = "tui"))]
+fn check_terminal() -> Result<String> {
+    Ok("not needed; this build has no TUI".to_string())
+}
+
+/// Round-trips a small sample through the same code paths as `--stdout` and `--decompress`.
+fn check_zstd(opts: &compressor::CompressOptions) -> Result<String> {
+    let dir = std::env::temp_dir().join(format!("piper-doctor-{}", std::process::id()));
+    let result = (|| {
+        std::fs::create_dir_all(&dir)?;
+        let sample: Vec<u8> = b"piper doctor sample\n".repeat(256);
+        std::fs::write(dir.join("sample"), &sample)?;
+
+        let mut compressed = Vec::new();
+        compressor::compress_to_writer(&dir.join("sample"), &mut compressed, opts)?;
+        compressor::decompress_reader(compressed.as_slice(), compressor::StreamFormat::Zst, &dir.join("restored"))?;
+        anyhow::ensure!(std::fs::read(dir.join("restored"))? == sample, "Round trip changed the sample");
+        Ok(format!("{} (level {}, {} -> {} bytes)",
+            zstd::zstd_safe::version_string(), opts.level, sample.len(), compressed.len()))
+    })();
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// Best-effort guess from the locale, the same variables libc consults.
+/// The Linux console and `dumb` terminals lack the glyphs even with a UTF-8 locale.
+fn terminal_supports_unicode() -> bool {
+    if cfg!(windows) {
+        return true;
+    }
+    if matches!(std::env::var("TERM").as_deref(), Ok("linux") | Ok("dumb")) {
+        return false;
+    }
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_default()
+        .to_uppercase();
+    locale.contains("UTF-8") || locale.contains("UTF8")
+}
+
+/// `q`, or Ctrl-C: raw mode delivers it as a key instead of a SIGINT.
+#[cfg(feature = "tui")]
+fn is_quit(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('q') => true,
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "tui")]
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> 
 where
     <B as Backend>::Error: Send + Sync + 'static,
 {
+    let mut last_tick = std::time::Instant::now();
     loop {
         terminal.draw(|f| ui::draw(f, app))?;
 
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                if let KeyCode::Char('q') = key.code {
-                    return Ok(());
-                }
-                // Handle other keys
-                app.handle_input(key.code);
+        // Keys redraw right away; the tick only paces spinner and worker updates
+        let timeout = app.tick_rate.saturating_sub(last_tick.elapsed());
+        if event::poll(timeout)?
+            && let Event::Key(key) = event::read()?
+        {
+            if is_quit(&key) {
+                return Ok(());
             }
+            // Handle other keys
+            app.handle_input(key);
+        }
+
+        if last_tick.elapsed() >= app.tick_rate {
+            app.tick();
+            last_tick = std::time::Instant::now();
         }
-        
-        // Handle background updates here if needed
-        app.tick();
     }
 }
@@ -0,0 +1,83 @@
+//! Rough zstd memory needs, so a batch of parallel jobs at a high level
+//! doesn't get Piper OOM-killed on a small machine.
+
+use std::path::Path;
+
+use crate::compressor::{self, Backend, CompressOptions};
+
+/// Share of physical RAM a compression batch may use by default.
+pub const DEFAULT_MEMORY_FRACTION: f64 = 0.5;
+
+/// Approximate encoder memory per job for levels 1..=22, in MiB: the window
+/// plus the match-finder tables zstd picks for large inputs.
+const LEVEL_MIB: [u64; 22] = [1, 2, 3, 4, 5, 5, 8, 8, 16, 28, 28, 36, 52, 52, 68, 40, 56, 56, 88, 192, 384, 768];
+/// On top of that for `Backend::ZstdLong`: its 128 MiB window and the
+/// long-distance match table.
+const LONG_MIB: u64 = 192;
+
+/// Estimated bytes one encoder at `level` needs.
+pub fn per_job(level: i32, backend: Backend) -> u64 {
+    let index = (level.clamp(compressor::MIN_LEVEL, compressor::MAX_LEVEL) - 1) as usize;
+    let long = if backend == Backend::ZstdLong { LONG_MIB } else { 0 };
+    (LEVEL_MIB[index] + long) * 1024 * 1024
+}
+
+/// Physical RAM, where the platform says; `None` disables the guard.
+pub fn total_memory() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        let meminfo = std::fs::read_to_string(Path::new("/proc/meminfo")).ok()?;
+        let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+        let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kib * 1024)
+    } else if cfg!(target_os = "macos") {
+        let out = std::process::Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+        String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+    } else {
+        None
+    }
+}
+
+/// How a batch was cut down to fit in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fit {
+    pub jobs: usize,
+    /// Highest level any job may use; `peak_level` when that fits.
+    pub max_level: i32,
+}
+
+/// Fewest cuts that keep `jobs` encoders at up to `peak_level` under
+/// `budget` bytes: fewer jobs first, and a lower level only when even one
+/// job would not fit.
+pub fn fit(jobs: usize, peak_level: i32, backend: Backend, budget: u64) -> Fit {
+    let fitting = (budget / per_job(peak_level, backend)) as usize;
+    if fitting >= 1 {
+        return Fit { jobs: jobs.min(fitting).max(1), max_level: peak_level };
+    }
+    let max_level = (compressor::MIN_LEVEL..peak_level).rev()
+        .find(|&level| per_job(level, backend) <= budget)
+        .unwrap_or(compressor::MIN_LEVEL);
+    Fit { jobs: 1, max_level }
+}
+
+/// `fit` for `jobs` workers compressing with `opts`, given `total` bytes of
+/// RAM of which `fraction` may be used.
+pub fn fit_options(jobs: usize, opts: &CompressOptions, total: u64, fraction: f64) -> Fit {
+    let budget = (total as f64 * fraction.clamp(0.05, 1.0)) as u64;
+    fit(jobs, compressor::peak_level(opts), opts.backend, budget)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_cuts_jobs_before_level() {
+        let gib = 1024 * 1024 * 1024;
+        // Level 19 is ~88 MiB a job: 1 GiB fits 11 of them
+        assert_eq!(fit(16, 19, Backend::Zstd, gib), Fit { jobs: 11, max_level: 19 });
+        assert_eq!(fit(4, 19, Backend::Zstd, gib), Fit { jobs: 4, max_level: 19 });
+        // Level 22 needs 768 MiB; with 512 MiB only a lower level fits at all
+        assert_eq!(fit(8, 22, Backend::Zstd, gib / 2), Fit { jobs: 1, max_level: 21 });
+        assert!(per_job(3, Backend::ZstdLong) > per_job(19, Backend::Zstd));
+    }
+}
@@ -0,0 +1,183 @@
+//! Artifact file names: how `app.log` becomes `app.log.zst`, and back.
+//!
+//! Compressing, restoring and the scanner's "already compressed" check all go
+//! through [`Naming`], so a custom template (`[compress] naming`) means the
+//! same thing everywhere.
+
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use anyhow::{Result, bail};
+
+/// `app.log` -> `app.log.zst`; `Makefile` -> `Makefile.zst`.
+pub const DEFAULT_FILE_TEMPLATE: &str = "{name}.{ext}.zst";
+/// `node_modules` -> `node_modules.tar.zst`.
+pub const DEFAULT_DIR_TEMPLATE: &str = "{name}.tar.zst";
+
+/// A template split around its placeholders: `prefix{name}middle{ext}suffix`,
+/// or `prefix{name}middle` without `{ext}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Template {
+    prefix: String,
+    middle: String,
+    suffix: String,
+    has_ext: bool,
+}
+
+impl Template {
+    fn parse(template: &str, allow_ext: bool) -> Result<Self> {
+        let Some((prefix, rest)) = template.split_once("{name}") else {
+            bail!("naming template {:?} has no {{name}}", template);
+        };
+        if prefix.contains("{ext}") || rest.contains("{name}") {
+            bail!("naming template {:?} needs one {{name}}, before any {{ext}}", template);
+        }
+        let (middle, suffix, has_ext) = match rest.split_once("{ext}") {
+            Some(_) if !allow_ext => bail!("folder template {:?} can't use {{ext}}", template),
+            Some((middle, suffix)) => (middle, suffix, true),
+            None => (rest, "", false),
+        };
+        if suffix.contains("{ext}") {
+            bail!("naming template {:?} uses {{ext}} twice", template);
+        }
+        // Otherwise the name and extension can't be told apart again
+        if has_ext && !middle.ends_with('.') {
+            bail!("naming template {:?} needs a `.` right before {{ext}}", template);
+        }
+        if template.contains(['/', '\\']) {
+            bail!("naming template {:?} must be a file name, not a path", template);
+        }
+        // Anything that could render to the source's own name would overwrite it
+        if prefix.is_empty() && suffix.is_empty() && middle.trim_end_matches('.').is_empty() {
+            bail!("naming template {:?} must add something to the name", template);
+        }
+        Ok(Template { prefix: prefix.to_string(), middle: middle.to_string(), suffix: suffix.to_string(), has_ext })
+    }
+
+    fn render(&self, name: &OsStr, ext: Option<&OsStr>) -> OsString {
+        let mut out = OsString::from(&self.prefix);
+        out.push(name);
+        match ext {
+            Some(ext) => {
+                out.push(&self.middle);
+                out.push(ext);
+            }
+            None => out.push(self.middle_without_ext()),
+        }
+        out.push(&self.suffix);
+        out
+    }
+
+    /// With `{ext}` but no extension to fill in, a `.` right before it goes too.
+    fn middle_without_ext(&self) -> &str {
+        if self.has_ext {
+            self.middle.strip_suffix('.').unwrap_or(&self.middle)
+        } else {
+            &self.middle
+        }
+    }
+
+    /// The source name `artifact` was rendered from, if it was.
+    fn source_name(&self, artifact: &str) -> Option<String> {
+        let body = artifact.strip_prefix(self.prefix.as_str())?;
+        if self.has_ext
+            && let Some(body) = body.strip_suffix(self.suffix.as_str())
+            && let Some(at) = body.rfind(self.middle.as_str())
+            && at > 0
+            && at + self.middle.len() < body.len()
+        {
+            return Some(format!("{}.{}", &body[..at], &body[at + self.middle.len()..]));
+        }
+        let tail = format!("{}{}", self.middle_without_ext(), self.suffix);
+        body.strip_suffix(tail.as_str()).filter(|name| !name.is_empty()).map(str::to_string)
+    }
+}
+
+/// How artifacts are named, for files and for folders.
+///
+/// `{name}` is the source's name and `{ext}` its extension; with `{ext}` in
+/// the file template, `{name}` is the name without it. Folders only get `{name}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naming {
+    file: Template,
+    dir: Template,
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Naming::new(DEFAULT_FILE_TEMPLATE, DEFAULT_DIR_TEMPLATE).expect("default templates are valid")
+    }
+}
+
+impl Naming {
+    /// e.g. `Naming::new("{name}-piper.{ext}.zst", "{name}.tzst")`.
+    pub fn new(file: &str, dir: &str) -> Result<Self> {
+        Ok(Naming { file: Template::parse(file, true)?, dir: Template::parse(dir, false)? })
+    }
+
+    /// File name of the artifact for a source named `source`.
+    pub fn artifact_name(&self, source: &OsStr, is_dir: bool) -> OsString {
+        let path = Path::new(source);
+        match (is_dir, path.extension()) {
+            (true, _) => self.dir.render(source, None),
+            (false, Some(ext)) if self.file.has_ext => self.file.render(path.file_stem().unwrap_or(source), Some(ext)),
+            (false, _) => self.file.render(source, None),
+        }
+    }
+
+    /// The source name and whether it was a folder, for an artifact named
+    /// like this. Folders are tried first: their artifacts tend to look like
+    /// a file artifact too (`x.tar.zst` vs `x.tar` + `.zst`).
+    pub fn source_name(&self, artifact: &Path) -> Option<(String, bool)> {
+        let name = artifact.file_name()?.to_string_lossy();
+        self.dir.source_name(&name).map(|source| (source, true))
+            .or_else(|| self.file.source_name(&name).map(|source| (source, false)))
+    }
+
+    /// Whether `path` is named like one of our artifacts.
+    pub fn is_artifact(&self, path: &Path) -> bool {
+        self.source_name(path).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(naming: &Naming, source: &str, is_dir: bool) -> (String, Option<(String, bool)>) {
+        let artifact = naming.artifact_name(OsStr::new(source), is_dir).to_string_lossy().to_string();
+        let back = naming.source_name(Path::new(&artifact));
+        (artifact, back)
+    }
+
+    #[test]
+    fn test_default_names_match_the_fixed_scheme() {
+        let naming = Naming::default();
+        assert_eq!(round_trip(&naming, "app.log", false), ("app.log.zst".into(), Some(("app.log".into(), false))));
+        assert_eq!(round_trip(&naming, "Makefile", false), ("Makefile.zst".into(), Some(("Makefile".into(), false))));
+        assert_eq!(round_trip(&naming, "dump.tar.gz", false), ("dump.tar.gz.zst".into(), Some(("dump.tar.gz".into(), false))));
+        assert_eq!(round_trip(&naming, "node_modules", true), ("node_modules.tar.zst".into(), Some(("node_modules".into(), true))));
+        assert!(!naming.is_artifact(Path::new("notes.txt")));
+    }
+
+    #[test]
+    fn test_custom_templates_round_trip() -> Result<()> {
+        let naming = Naming::new("{name}-piper.{ext}.zst", "{name}.tzst")?;
+        assert_eq!(round_trip(&naming, "app.log", false), ("app-piper.log.zst".into(), Some(("app.log".into(), false))));
+        assert_eq!(round_trip(&naming, "README", false), ("README-piper.zst".into(), Some(("README".into(), false))));
+        assert_eq!(round_trip(&naming, "target", true), ("target.tzst".into(), Some(("target".into(), true))));
+        assert!(!naming.is_artifact(Path::new("app.log.zst")), "Default-named files aren't ours under a custom scheme");
+
+        let naming = Naming::new("{name}.zst", "archived-{name}.tar.zst")?;
+        assert_eq!(round_trip(&naming, "app.log", false), ("app.log.zst".into(), Some(("app.log".into(), false))));
+        assert_eq!(round_trip(&naming, "venv", true), ("archived-venv.tar.zst".into(), Some(("venv".into(), true))));
+
+        // Templates that could overwrite sources, or aren't names, are refused
+        assert!(Naming::new("{name}.{ext}", DEFAULT_DIR_TEMPLATE).is_err());
+        assert!(Naming::new("{ext}.{name}.zst", DEFAULT_DIR_TEMPLATE).is_err());
+        assert!(Naming::new("{name}{ext}.zst", DEFAULT_DIR_TEMPLATE).is_err());
+        assert!(Naming::new(DEFAULT_FILE_TEMPLATE, "{name}.{ext}.tar.zst").is_err());
+        assert!(Naming::new(DEFAULT_FILE_TEMPLATE, "out/{name}.tar.zst").is_err());
+        assert!(Naming::new("app.zst", DEFAULT_DIR_TEMPLATE).is_err());
+        Ok(())
+    }
+}
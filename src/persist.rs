@@ -0,0 +1,66 @@
+//! Crash-safe writes for Piper's own files (history, recent scans, sidecars).
+//!
+//! A reader only ever sees the old file or the new one: contents go to
+//! `<file>.tmp` first and are renamed over the file once complete, the same
+//! way artifacts are put in place.
+
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes `path` through a temp file next to it. If `write` (or anything
+/// after it) fails, the temp file is removed and `path` is left as it was.
+pub fn write_atomic(path: &Path, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+    let temp = sibling(path, "tmp");
+    let result = (|| {
+        let mut file = BufWriter::new(File::create(&temp)?);
+        write(&mut file)?;
+        file.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// `value` as pretty JSON, via `write_atomic`.
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    write_atomic(path, |out| Ok(serde_json::to_writer_pretty(out, value)?))
+}
+
+/// `history.json` -> `history.json.<suffix>`, next to it.
+pub(crate) fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupted_write_keeps_the_previous_file() -> io::Result<()> {
+        let dir = PathBuf::from("test_persist_interrupted");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("history.json");
+        write_json(&path, &vec![1, 2, 3])?;
+
+        // Dies halfway through, like a crash or a full disk would
+        let interrupted = write_atomic(&path, |out| {
+            out.write_all(b"[1, 2, 3, 4")?;
+            Err(io::Error::other("disk full"))
+        });
+        let content = fs::read_to_string(&path)?;
+        let leftovers = fs::read_dir(&dir)?.count();
+
+        fs::remove_dir_all(&dir)?;
+        assert!(interrupted.is_err());
+        assert_eq!(serde_json::from_str::<Vec<i32>>(&content)?, vec![1, 2, 3]);
+        assert_eq!(leftovers, 1, "The temp file is cleaned up");
+        Ok(())
+    }
+}
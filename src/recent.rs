@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+
+use crate::backup;
+use crate::persist;
+
+/// How many roots the Home screen remembers.
+pub const MAX_RECENT: usize = 5;
+
+/// A scan root and when it was last crawled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentScan {
+    pub path: PathBuf,
+    pub last_scanned: i64, // Unix seconds
+}
+
+/// Recently scanned roots, most recent first, persisted as JSON.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecentScans {
+    pub scans: Vec<RecentScan>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl RecentScans {
+    /// `recent.json` in the data directory (see `backup::data_dir`).
+    pub fn default_path() -> Option<PathBuf> {
+        backup::data_dir().map(|dir| dir.join("recent.json"))
+    }
+
+    /// The list saved at `path`, or an empty one if there is none (or it's unreadable).
+    pub fn load_from(path: Option<PathBuf>) -> Self {
+        let recent: Self = path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        RecentScans { path, ..recent }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = self.path.as_ref().context("No data directory to remember scans in")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        persist::write_json(path, self)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Moves `root` to the top (adding it if new), keeps the newest
+    /// `MAX_RECENT`, and saves.
+    pub fn record(&mut self, root: &Path) -> Result<()> {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        self.scans.retain(|scan| scan.path != root);
+        self.scans.insert(0, RecentScan { path: root, last_scanned: chrono::Local::now().timestamp() });
+        self.scans.truncate(MAX_RECENT);
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_keeps_newest_unique_roots() -> Result<()> {
+        let dir = PathBuf::from("test_recent_scans");
+        let mut recent = RecentScans::load_from(Some(dir.join("recent.json")));
+
+        for i in 0..MAX_RECENT + 2 {
+            recent.record(Path::new(&format!("/nonexistent/root{}", i)))?;
+        }
+        recent.record(Path::new("/nonexistent/root3"))?;
+
+        let reloaded = RecentScans::load_from(Some(dir.join("recent.json")));
+        fs::remove_dir_all(&dir)?;
+        let paths: Vec<_> = reloaded.scans.iter().map(|s| s.path.clone()).collect();
+        assert_eq!(paths.len(), MAX_RECENT);
+        assert_eq!(paths[0], PathBuf::from("/nonexistent/root3"));
+        assert_eq!(paths[1], PathBuf::from("/nonexistent/root6"));
+        assert_eq!(paths.iter().filter(|p| p.ends_with("root3")).count(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{Result, Context};
+
+use crate::persist;
+
+/// Metadata stored next to an artifact (`<artifact>.piper.json`) for things
+/// the artifact name alone can't tell a restore, like where the source lived.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub original_path: PathBuf,
+    /// Where the artifact was written, to tell whether it has been moved since.
+    /// Missing in sidecars from older versions.
+    #[serde(default)]
+    pub artifact_path: Option<PathBuf>,
+    /// Set when `original_path` was a folder holding just this one file,
+    /// compressed on its own without a tar wrapper. Restore recreates the
+    /// folder around it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_path: Option<PathBuf>,
+    /// When the artifact was written. Restores can stamp the restored item
+    /// with it, so build tools see it as no newer than their last output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<SystemTime>,
+    /// The source's own mtime at compression, put back on restore. For a
+    /// folder that's the top-level folder; the tar keeps everything inside.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_mtime: Option<SystemTime>,
+}
+
+impl Sidecar {
+    pub fn path_for(artifact: &Path) -> PathBuf {
+        let mut name = artifact.as_os_str().to_os_string();
+        name.push(".piper.json");
+        PathBuf::from(name)
+    }
+
+    pub fn write(&self, artifact: &Path) -> Result<()> {
+        persist::write_json(&Self::path_for(artifact), self)
+            .context("Failed to write sidecar")?;
+        Ok(())
+    }
+
+    /// `None` if the artifact has no (readable) sidecar.
+    pub fn read(artifact: &Path) -> Option<Self> {
+        let content = fs::read_to_string(Self::path_for(artifact)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// True when the artifact no longer sits where it was written.
+    pub fn artifact_moved(&self, artifact: &Path) -> bool {
+        match (&self.artifact_path, std::path::absolute(artifact)) {
+            (Some(recorded), Ok(current)) => *recorded != current,
+            _ => false,
+        }
+    }
+
+    pub fn remove(artifact: &Path) {
+        let _ = fs::remove_file(Self::path_for(artifact));
+    }
+}
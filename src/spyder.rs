@@ -1,46 +1,301 @@
 
 use ignore::WalkBuilder;
+use ignore::overrides::OverrideBuilder;
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use rayon::prelude::*;
 use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use serde::Deserialize;
+
+use crate::compressor::{RECENT_REASON, SPARSE_REASON};
+use crate::naming::Naming;
+
+/// Files at or above this size are worth listing on their own.
+const LARGE_FILE_THRESHOLD: u64 = 1024 * 1024; // 1MB
+
+/// Piper-specific ignore rules, same syntax as .gitignore.
+pub const PIPER_IGNORE_FILENAME: &str = ".piperignore";
+
+/// Folders not walked into by default: other tools' internal state, which
+/// is slow to crawl and unsafe to compress. `.git` is skipped regardless.
+pub const DEFAULT_SKIP_DIRS: &[&str] = &[".hg", ".svn", ".jj", ".terraform", ".cache"];
 
 pub struct Spyder {
     root: PathBuf,
+    exclude: Vec<String>, // Globs from config `exclude`
+    exclude_extensions: Vec<String>, // Lowercase, without the dot
+    big_file_threshold: Option<u64>, // Flag any file this large; off by default
+    time_basis: TimeBasis, // Which timestamp ages and staleness are measured from
+    naming: Naming, // Files named like artifacts are already compressed, never candidates
+    skip_dirs: Vec<String>, // Folder names never walked into, besides .git
+    protect_recent: Option<Duration>, // Heavy folders changed this recently are tagged, not offered
+}
+
+/// Which file timestamp counts as "last touched". Access times are often
+/// frozen by `noatime` mounts, so modified time can be the better signal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeBasis {
+    #[default]
+    Accessed,
+    Modified,
+    Created,
+}
+
+impl TimeBasis {
+    /// As it reads in a reason, e.g. "30 days since modified".
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeBasis::Accessed => "accessed",
+            TimeBasis::Modified => "modified",
+            TimeBasis::Created => "created",
+        }
+    }
+
+    /// This basis, then the others to try when the platform doesn't report it.
+    fn fallbacks(self) -> [TimeBasis; 3] {
+        match self {
+            TimeBasis::Accessed => [TimeBasis::Accessed, TimeBasis::Modified, TimeBasis::Created],
+            TimeBasis::Modified => [TimeBasis::Modified, TimeBasis::Created, TimeBasis::Accessed],
+            TimeBasis::Created => [TimeBasis::Created, TimeBasis::Modified, TimeBasis::Accessed],
+        }
+    }
+
+    /// How long ago the first available timestamp in the fallback chain was,
+    /// and which one that was.
+    pub fn age(self, metadata: &std::fs::Metadata) -> Option<(Duration, TimeBasis)> {
+        self.fallbacks().into_iter().find_map(|basis| {
+            let time = match basis {
+                TimeBasis::Accessed => metadata.accessed(),
+                TimeBasis::Modified => metadata.modified(),
+                TimeBasis::Created => metadata.created(),
+            };
+            let age = SystemTime::now().duration_since(time.ok()?).ok()?;
+            Some((age, basis))
+        })
+    }
+}
+
+/// Why an item is a candidate. Group and filter on this, not on `reason()` text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    HeavyDir, // node_modules, target, venv, .venv
+    StaleLog, // log/txt/old over 1MB, untouched for 30 days
+    LargeFile, // A big file found by expanding a heavy directory
+    BigFile, // Any file over the configured `large_file_mb`, whatever its type or age
+    Duplicate, // Same content as another candidate; not detected yet
+    AlreadyCompressedSkip, // An existing artifact (see naming::Naming); not a candidate itself
+    Listed, // Named in a `--paths-from` list rather than found by a scan
+}
+
+impl Category {
+    /// Short label for grouping, e.g. in summaries.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Category::HeavyDir => "Heavy folder",
+            Category::StaleLog => "Stale log",
+            Category::LargeFile => "Large file",
+            Category::BigFile => "Big file",
+            Category::Duplicate => "Duplicate",
+            Category::AlreadyCompressedSkip => "Already compressed",
+            Category::Listed => "Listed",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ScannedItem {
     pub path: PathBuf,
     pub size: u64,
-    pub reason: String, // "heavy_node_modules", "stale_log", etc.
+    pub category: Category,
+    pub age_days: Option<u64>, // Days since `age_basis`, when the platform reports a timestamp
+    pub age_basis: Option<TimeBasis>, // The timestamp `age_days` was measured from, after fallbacks
+    pub file_count: Option<u64>, // Files inside, for directory candidates
+    pub skip_reason: Option<&'static str>, // Listed but left alone, e.g. compressor::RECENT_REASON
+}
+
+/// Totals from one walk over a directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirStats {
+    pub size: u64,
+    pub files: u64,
+    pub modified: Option<SystemTime>, // Newest modified time of anything inside, folders included
+}
+
+impl ScannedItem {
+    /// Human-readable reason shown in the Type column.
+    pub fn reason(&self) -> String {
+        if let Some(reason) = self.skip_reason {
+            return reason.to_string();
+        }
+        let name_of = |p: Option<&Path>| {
+            p.and_then(|p| p.file_name()).unwrap_or_default().to_string_lossy().to_string()
+        };
+        match self.category {
+            Category::HeavyDir => format!("Heavy Dependency Folder: {}", name_of(Some(&self.path))),
+            Category::StaleLog => format!(
+                "Stale Log File (>30 days since {})",
+                self.age_basis.unwrap_or_default().name(),
+            ),
+            Category::LargeFile => format!("Large File in {}", name_of(self.path.parent())),
+            Category::BigFile => "Large File".to_string(),
+            Category::Duplicate => "Duplicate File".to_string(),
+            Category::AlreadyCompressedSkip => "Already compressed".to_string(),
+            Category::Listed => "Listed in --paths-from".to_string(),
+        }
+    }
+}
+
+/// Everything a crawl produced, including what it had to skip.
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub items: Vec<ScannedItem>,
+    pub skipped: Vec<String>, // One reason per unreadable path, e.g. "permission denied"
+    pub visited: u64, // Files and folders walked, candidates or not
+}
+
+impl ScanReport {
+    /// Groups skip reasons into "12 paths skipped: permission denied" lines.
+    pub fn skipped_summary(&self) -> Vec<String> {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for reason in &self.skipped {
+            match counts.iter_mut().find(|(r, _)| *r == reason.as_str()) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((reason, 1)),
+            }
+        }
+        counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+        counts.into_iter()
+            .map(|(reason, n)| format!("{} path{} skipped: {}", n, if n == 1 { "" } else { "s" }, reason))
+            .collect()
+    }
 }
 
 impl Spyder {
     pub fn new<P: AsRef<Path>>(root: P) -> Self {
         Self {
             root: root.as_ref().to_path_buf(),
+            exclude: Vec::new(),
+            exclude_extensions: Vec::new(),
+            big_file_threshold: None,
+            time_basis: TimeBasis::default(),
+            naming: Naming::default(),
+            skip_dirs: DEFAULT_SKIP_DIRS.iter().map(|d| d.to_string()).collect(),
+            protect_recent: None,
         }
     }
 
+    /// Globs that are never crawled, e.g. `**/vendor` or `*.sqlite`.
+    pub fn exclude(mut self, globs: Vec<String>) -> Self {
+        self.exclude = globs;
+        self
+    }
+
+    /// File extensions never listed, e.g. `sqlite` or `.db` (dot and case don't matter).
+    /// Meant for files that are risky to touch while another process has them open.
+    pub fn exclude_extensions(mut self, extensions: Vec<String>) -> Self {
+        self.exclude_extensions = extensions.iter()
+            .map(|e| e.trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Also list any file of at least `bytes`, regardless of extension or age.
+    pub fn big_files(mut self, bytes: Option<u64>) -> Self {
+        self.big_file_threshold = bytes;
+        self
+    }
+
+    /// Measure ages, and so staleness, from this timestamp (falling back
+    /// to the others where it's unavailable).
+    pub fn time_basis(mut self, basis: TimeBasis) -> Self {
+        self.time_basis = basis;
+        self
+    }
+
+    /// Treat files named like `naming`'s artifacts as already compressed.
+    pub fn naming(mut self, naming: Naming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Folder names never walked into or listed, instead of `DEFAULT_SKIP_DIRS`.
+    pub fn skip_dirs(mut self, names: Vec<String>) -> Self {
+        self.skip_dirs = names;
+        self
+    }
+
+    /// List heavy folders with anything modified within `window` as skipped
+    /// (`RECENT_REASON`), e.g. a `target/` a build is still writing to.
+    pub fn protect_recent(mut self, window: Option<Duration>) -> Self {
+        self.protect_recent = window;
+        self
+    }
+
+    fn is_excluded_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .is_some_and(|ext| self.exclude_extensions.contains(&ext))
+    }
+
     /// The "Middle-Out" Parallel Crawler.
     /// Uses 'ignore' crate to respect .gitignore, and Rayon for parallel processing.
-    pub fn crawl(&self) -> Vec<ScannedItem> {
+    /// Unreadable paths don't abort the crawl; they are counted in `skipped`.
+    ///
+    /// Ignore precedence (highest first), as defined by the `ignore` crate:
+    /// 1. config `exclude` globs
+    /// 2. `.piperignore` files (always honored)
+    /// 3. `.gitignore` (currently disabled so `target` folders are found)
+    pub fn crawl(&self) -> ScanReport {
         // Step 1: Walk with .gitignore support
-        let walker = WalkBuilder::new(&self.root)
+        let mut builder = WalkBuilder::new(&self.root);
+        builder
             .hidden(false) 
             .git_ignore(false) // Temporarily disable gitignore to find 'target' folders
-            .build();
+            .add_custom_ignore_filename(PIPER_IGNORE_FILENAME);
+
+        // Prune rather than filter, so nothing inside is even read. The
+        // root itself is walked even if named like one (`piper --scan ~/.cache`).
+        let skip_dirs = self.skip_dirs.clone();
+        builder.filter_entry(move |e| {
+            e.depth() == 0 || !(e.file_type().is_some_and(|ft| ft.is_dir()) && is_skipped_dir(&skip_dirs, e.file_name()))
+        });
+
+        if !self.exclude.is_empty() {
+            let mut overrides = OverrideBuilder::new(&self.root);
+            for glob in &self.exclude {
+                // Overrides whitelist by default; "!" turns a glob into an ignore rule.
+                // Invalid globs are reported up-front by `invalid_globs`.
+                let _ = overrides.add(&format!("!{}", glob));
+            }
+            if let Ok(o) = overrides.build() {
+                builder.overrides(o);
+            }
+        }
+
+        let walker = builder.build();
 
         // Step 2: Parallel Heuristic Analysis
         let results = Arc::new(Mutex::new(Vec::new()));
+        let skipped = Arc::new(Mutex::new(Vec::new()));
+        let visited = AtomicU64::new(0);
         
         // Use par_bridge to parallelize the stream
         walker.par_bridge().for_each(|entry| {
-            if let Ok(e) = entry {
-                if let Some(item) = self.analyze_entry(&e) {
-                    if let Ok(mut lock) = results.lock() {
-                        lock.push(item);
+            match entry {
+                Ok(e) => {
+                    visited.fetch_add(1, Ordering::Relaxed);
+                    if let Some(item) = self.analyze_entry(&e)
+                        && let Ok(mut lock) = results.lock()
+                    {
+                        lock.push(flag_sparse(item));
+                    }
+                }
+                Err(err) => {
+                    if let Ok(mut lock) = skipped.lock() {
+                        lock.push(describe_walk_error(&err));
                     }
                 }
             }
@@ -52,17 +307,72 @@ impl Spyder {
         };
         
         // Sort by size (descending) to prioritize big wins
-        final_results.sort_by(|a, b| b.size.cmp(&a.size));
+        final_results.sort_by_key(|item| std::cmp::Reverse(item.size));
+
+        let skipped = match skipped.lock() {
+            Ok(guard) => guard.clone(),
+            Err(_) => Vec::new(),
+        };
         
-        final_results
+        ScanReport {
+            items: final_results,
+            skipped,
+            visited: visited.into_inner(),
+        }
+    }
+
+    /// Fine-grained crawl: lists the individual large files under the root.
+    /// Used to break a heavy directory candidate down into per-file choices.
+    pub fn large_files(&self) -> Vec<ScannedItem> {
+        use walkdir::WalkDir;
+
+        let mut results: Vec<ScannedItem> = WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !(e.file_type().is_dir() && is_skipped_dir(&self.skip_dirs, e.file_name())))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| !self.is_excluded_extension(e.path()) && !self.naming.is_artifact(e.path()))
+            .filter_map(|e| {
+                let metadata = e.metadata().ok()?;
+                if metadata.len() < LARGE_FILE_THRESHOLD {
+                    return None;
+                }
+                let (age_days, age_basis) = self.age_days(&metadata);
+                Some(ScannedItem {
+                    path: e.path().to_path_buf(),
+                    size: metadata.len(),
+                    category: Category::LargeFile,
+                    age_days,
+                    age_basis,
+                    file_count: None,
+                    skip_reason: None,
+                })
+            })
+            .map(flag_sparse)
+            .collect();
+
+        results.sort_by_key(|item| std::cmp::Reverse(item.size));
+        results
+    }
+
+    /// Whole days since the entry was last touched, by `time_basis`, and the basis used.
+    fn age_days(&self, metadata: &std::fs::Metadata) -> (Option<u64>, Option<TimeBasis>) {
+        match self.time_basis.age(metadata) {
+            Some((age, basis)) => (Some(age.as_secs() / (24 * 60 * 60)), Some(basis)),
+            None => (None, None),
+        }
     }
 
     fn analyze_entry(&self, entry: &ignore::DirEntry) -> Option<ScannedItem> {
         let path = entry.path();
         let file_name = entry.file_name().to_string_lossy();
 
-        // Safety: Always skip .git to avoid corrupting repo history
-        if file_name == ".git" {
+        // Safety: never touch VCS and tool state (the walker prunes these
+        // too; this keeps the folder itself from becoming a candidate)
+        if entry.depth() > 0
+            && entry.file_type().is_some_and(|ft| ft.is_dir())
+            && is_skipped_dir(&self.skip_dirs, entry.file_name())
+        {
             return None;
         }
 
@@ -96,57 +406,318 @@ impl Spyder {
                     // For now, let's keep the check in case.
                     // Calculate actual size for the heavy folder to impress the user
                     // This might be expensive, but we are in a parallel thread, so it's acceptable.
-                    let size = self.get_dir_size(&path);
+                    let stats = dir_stats(path);
+                    if stats.size == 0 {
+                        // Freshly created or already cleaned; nothing to win
+                        return None;
+                    }
                     
+                    let (age_days, age_basis) = entry.metadata().map(|m| self.age_days(&m)).unwrap_or_default();
                     return Some(ScannedItem {
                         path: path.to_path_buf(),
-                        size,
-                        reason: format!("Heavy Dependency Folder: {}", file_name),
+                        size: stats.size,
+                        category: Category::HeavyDir,
+                        age_days,
+                        age_basis,
+                        file_count: Some(stats.files),
+                        skip_reason: self.protect_recent
+                            .filter(|&window| modified_within(stats.modified, window))
+                            .map(|_| RECENT_REASON),
                     });
                 }
                 return None;
             }
     
+            if ft.is_file() && (self.is_excluded_extension(path) || self.naming.is_artifact(path)) {
+                return None;
+            }
+
             // Check 2: Stale Logs
-            if ft.is_file() {
-                if let Some(ext) = path.extension() {
-                    let ext_str = ext.to_string_lossy();
-                     if ext_str == "log" || ext_str == "txt" || ext_str == "old" {
-                        if let Ok(metadata) = entry.metadata() {
-                            if metadata.len() > 1024 * 1024 { // > 1MB
-                                 // Check access time (30 days)
-                                let staleness_threshold = 30 * 24 * 60 * 60;
-                                let now = SystemTime::now();
-                                if let Ok(accessed) = metadata.accessed() {
-                                    if let Ok(duration) = now.duration_since(accessed) {
-                                        if duration.as_secs() > staleness_threshold {
-                                            return Some(ScannedItem {
-                                                path: path.to_path_buf(),
-                                                size: metadata.len(),
-                                                reason: "Stale Log File (>30 days)".to_string(),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                     }
+            if ft.is_file()
+                && let Some(ext) = path.extension()
+            {
+                let ext_str = ext.to_string_lossy();
+                if (ext_str == "log" || ext_str == "txt" || ext_str == "old")
+                    && let Ok(metadata) = entry.metadata()
+                    && metadata.len() > LARGE_FILE_THRESHOLD
+                {
+                    // Untouched for 30 days, by the configured time basis
+                    let staleness_threshold = 30 * 24 * 60 * 60;
+                    if let Some((duration, basis)) = self.time_basis.age(&metadata)
+                        && duration.as_secs() > staleness_threshold
+                    {
+                        return Some(ScannedItem {
+                            path: path.to_path_buf(),
+                            size: metadata.len(),
+                            category: Category::StaleLog,
+                            age_days: Some(duration.as_secs() / (24 * 60 * 60)),
+                            age_basis: Some(basis),
+                            file_count: None,
+                            skip_reason: None,
+                        });
+                    }
                 }
             }
+
+            // Check 3: Big files of any kind, when enabled
+            if ft.is_file()
+                && let Some(threshold) = self.big_file_threshold
+                && let Ok(metadata) = entry.metadata()
+                && metadata.len() >= threshold
+            {
+                let (age_days, age_basis) = self.age_days(&metadata);
+                return Some(ScannedItem {
+                    path: path.to_path_buf(),
+                    size: metadata.len(),
+                    category: Category::BigFile,
+                    age_days,
+                    age_basis,
+                    file_count: None,
+                    skip_reason: None,
+                });
+            }
         }
 
         None
     }
+}
 
-    fn get_dir_size(&self, path: &Path) -> u64 {
-        use walkdir::WalkDir;
-        
-        WalkDir::new(path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter_map(|e| e.metadata().ok())
-            .filter(|m| m.is_file())
-            .map(|m| m.len())
-            .sum()
+/// Size, file count and newest change of everything under `path`, in a single walk.
+pub fn dir_stats(path: &Path) -> DirStats {
+    use walkdir::WalkDir;
+
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .fold(DirStats::default(), |acc, m| {
+            // Folder times count too: deleting a file only touches its parent
+            let modified = acc.modified.max(m.modified().ok());
+            if m.is_file() {
+                DirStats { size: acc.size + m.len(), files: acc.files + 1, modified }
+            } else {
+                DirStats { modified, ..acc }
+            }
+        })
+}
+
+/// Files smaller than this are never reported as sparse; the savings at
+/// stake are too small to be worth a second look.
+const SPARSE_MIN_SIZE: u64 = 1024 * 1024;
+
+/// Bytes actually allocated on disk, where the platform reports it.
+#[cfg(unix)]
+pub fn allocated_size(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.blocks() * 512) // st_blocks is always in 512-byte units
+}
+
+#[cfg(not(unix))]
+pub fn allocated_size(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// A file taking up less than half its length on disk: a VM image or
+/// database with holes, or one a filesystem already compresses. Either way
+/// compressing it reads gigabytes of nothing for little or no real saving.
+/// Never true where `allocated_size` is unknown.
+pub fn is_sparse(metadata: &std::fs::Metadata) -> bool {
+    metadata.is_file()
+        && metadata.len() >= SPARSE_MIN_SIZE
+        && allocated_size(metadata).is_some_and(|allocated| allocated < metadata.len() / 2)
+}
+
+/// Lists a sparse file candidate as skipped, at its real size on disk so it
+/// ranks below files that would actually free that much.
+fn flag_sparse(mut item: ScannedItem) -> ScannedItem {
+    if item.file_count.is_none()
+        && let Ok(metadata) = std::fs::metadata(&item.path)
+        && is_sparse(&metadata)
+    {
+        item.size = allocated_size(&metadata).unwrap_or(item.size);
+        item.skip_reason = Some(SPARSE_REASON);
+    }
+    item
+}
+
+/// Whether `modified` falls within `window` of now. Times in the future
+/// (clock skew, or a write racing the check) count as recent.
+pub fn modified_within(modified: Option<SystemTime>, window: Duration) -> bool {
+    modified.is_some_and(|time| time.elapsed().map_or(true, |age| age < window))
+}
+
+/// Files that mark the top of a project, e.g. one package in a monorepo.
+const PROJECT_MARKERS: &[&str] = &[
+    "package.json", "Cargo.toml", "pyproject.toml", "setup.py", "go.mod",
+    "pom.xml", "build.gradle", "Gemfile", "composer.json",
+];
+
+/// Nearest directory above `path`, no higher than `root`, that holds a
+/// project marker. Used to group candidates by the package they belong to.
+pub fn project_root(path: &Path, root: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(root))
+        .find(|dir| PROJECT_MARKERS.iter().any(|marker| dir.join(marker).is_file()))
+        .map(Path::to_path_buf)
+}
+
+/// Globs that `Spyder::exclude` would have to drop, so callers can warn about them.
+pub fn invalid_globs(globs: &[String]) -> Vec<String> {
+    globs.iter()
+        .filter(|g| OverrideBuilder::new(".").add(&format!("!{}", g)).is_err())
+        .cloned()
+        .collect()
+}
+
+/// `.git`, or one of `skip_dirs`.
+fn is_skipped_dir(skip_dirs: &[String], name: &OsStr) -> bool {
+    name == ".git" || skip_dirs.iter().any(|dir| OsStr::new(dir) == name)
+}
+
+/// Candidates from an explicit list (`--paths-from`) instead of a crawl, each
+/// measured as a scan would, largest first. Missing or unreadable paths are
+/// counted in `skipped`; repeats are listed once.
+pub fn measure_paths(mut paths: Vec<PathBuf>, time_basis: TimeBasis) -> ScanReport {
+    let mut seen = std::collections::HashSet::new();
+    paths.retain(|path| seen.insert(path.clone()));
+    let visited = paths.len() as u64;
+
+    let (found, missing): (Vec<_>, Vec<_>) = paths.into_par_iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(&path).map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => "not found".to_string(),
+                kind => describe_io_error(Some(kind)),
+            })?;
+            let (size, file_count) = if metadata.is_dir() {
+                let stats = dir_stats(&path);
+                (stats.size, Some(stats.files))
+            } else {
+                (metadata.len(), None)
+            };
+            let (age_days, age_basis) = match time_basis.age(&metadata) {
+                Some((age, basis)) => (Some(age.as_secs() / (24 * 60 * 60)), Some(basis)),
+                None => (None, None),
+            };
+            Ok(flag_sparse(ScannedItem { path, size, category: Category::Listed, age_days, age_basis, file_count, skip_reason: None }))
+        })
+        .partition(Result::is_ok);
+
+    let mut items: Vec<ScannedItem> = found.into_iter().filter_map(Result::ok).collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.size));
+    ScanReport {
+        items,
+        skipped: missing.into_iter().filter_map(Result::err).collect(),
+        visited,
+    }
+}
+
+/// Short, groupable reason for a path the walker couldn't read.
+fn describe_walk_error(err: &ignore::Error) -> String {
+    describe_io_error(err.io_error().map(|e| e.kind()))
+}
+
+fn describe_io_error(kind: Option<std::io::ErrorKind>) -> String {
+    match kind {
+        Some(std::io::ErrorKind::PermissionDenied) => "permission denied".to_string(),
+        Some(std::io::ErrorKind::NotFound) => "vanished during scan".to_string(),
+        Some(kind) => kind.to_string(),
+        None => "unreadable".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_big_files_are_opt_in() -> std::io::Result<()> {
+        let root = PathBuf::from("test_spyder_big_files");
+        std::fs::create_dir_all(&root)?;
+        std::fs::write(root.join("dataset.bin"), vec![0u8; 4096])?;
+        std::fs::write(root.join("small.bin"), vec![0u8; 16])?;
+
+        let default = Spyder::new(&root).crawl();
+        let enabled = Spyder::new(&root).big_files(Some(1024)).crawl();
+        std::fs::remove_dir_all(&root)?;
+
+        assert!(default.items.is_empty());
+        assert_eq!(default.visited, 3, "The root and both files");
+        assert_eq!(enabled.items.len(), 1);
+        assert_eq!(enabled.items[0].category, Category::BigFile);
+        assert!(enabled.items[0].path.ends_with("dataset.bin"));
+        assert_eq!(enabled.items[0].reason(), "Large File");
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcs_and_tool_state_is_not_walked() -> std::io::Result<()> {
+        let root = PathBuf::from("test_spyder_skip_dirs");
+        for dir in [".git", ".hg", ".cache", "data"] {
+            std::fs::create_dir_all(root.join(dir))?;
+            std::fs::write(root.join(dir).join("blob.bin"), vec![0u8; 4096])?;
+        }
+
+        let default = Spyder::new(&root).big_files(Some(1024)).crawl();
+        let opted_in = Spyder::new(&root).big_files(Some(1024)).skip_dirs(vec![".hg".into()]).crawl();
+        std::fs::remove_dir_all(&root)?;
+
+        let found = |report: &ScanReport| {
+            let mut dirs: Vec<String> = report.items.iter()
+                .filter_map(|i| i.path.parent()?.file_name().map(|n| n.to_string_lossy().to_string()))
+                .collect();
+            dirs.sort();
+            dirs
+        };
+        assert_eq!(found(&default), ["data"]);
+        assert_eq!(default.visited, 3, "The root, data and its file; nothing inside the others");
+        assert_eq!(found(&opted_in), [".cache", "data"], "A custom list replaces the defaults, but .git stays skipped");
+        Ok(())
+    }
+
+    #[test]
+    fn test_listed_paths_are_measured_not_crawled() -> std::io::Result<()> {
+        let root = PathBuf::from("test_spyder_listed");
+        std::fs::create_dir_all(root.join("build"))?;
+        std::fs::write(root.join("build").join("a.o"), vec![0u8; 300])?;
+        std::fs::write(root.join("build").join("b.o"), vec![0u8; 200])?;
+        std::fs::write(root.join("notes.txt"), vec![0u8; 10])?;
+
+        let report = measure_paths(vec![
+            root.join("notes.txt"),
+            root.join("build"),
+            root.join("missing"),
+            root.join("build"),
+        ], TimeBasis::default());
+        std::fs::remove_dir_all(&root)?;
+
+        let found: Vec<_> = report.items.iter().map(|i| (i.path.clone(), i.size, i.file_count)).collect();
+        assert_eq!(found, [(root.join("build"), 500, Some(2)), (root.join("notes.txt"), 10, None)]);
+        assert!(report.items.iter().all(|i| i.category == Category::Listed));
+        assert_eq!(report.skipped_summary(), ["1 path skipped: not found"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_basis_decides_staleness() -> std::io::Result<()> {
+        let root = PathBuf::from("test_spyder_time_basis");
+        std::fs::create_dir_all(&root)?;
+        let log = root.join("build.log");
+        std::fs::write(&log, vec![b'x'; 2 * 1024 * 1024])?;
+        // Written long ago but read just now, as on a mount that does update atime
+        let long_ago = SystemTime::now() - Duration::from_secs(60 * 24 * 60 * 60);
+        std::fs::File::options().write(true).open(&log)?
+            .set_times(std::fs::FileTimes::new().set_modified(long_ago).set_accessed(SystemTime::now()))?;
+
+        let by_access = Spyder::new(&root).crawl();
+        let by_mtime = Spyder::new(&root).time_basis(TimeBasis::Modified).crawl();
+        std::fs::remove_dir_all(&root)?;
+
+        assert!(by_access.items.is_empty());
+        assert_eq!(by_mtime.items.len(), 1);
+        assert_eq!(by_mtime.items[0].category, Category::StaleLog);
+        assert_eq!(by_mtime.items[0].age_days, Some(60));
+        assert_eq!(by_mtime.items[0].reason(), "Stale Log File (>30 days since modified)");
+        Ok(())
     }
 }
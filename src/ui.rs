@@ -1,24 +1,30 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect, Alignment},
-    style::{Color, Modifier, Style, Stylize},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        Block, Borders, Cell, Row, Table, List, ListItem, Paragraph, Tabs, Gauge, Chart, Axis, Dataset, GraphType
+        Bar, BarChart, Block, Borders, Cell, Gauge, Row, Table, TableState, Paragraph, Tabs
     },
-    symbols,
     Frame,
 };
 
-use crate::app::{App, FileStatus, AppTab, AppView};
+use std::path::{Path, PathBuf};
+
+use crate::app::{App, AppTab, FileItem, FileStatus, AppView, ListRow, Severity, SortOrder};
+use crate::spyder::Category;
+use crate::analytics;
+use crate::compressor;
+use crate::config::Column;
+use crate::units::{format_size, size_unit};
 
 pub fn draw(f: &mut Frame, app: &mut App) {
     match app.view {
-        AppView::Home => draw_home(f),
+        AppView::Home => draw_home(f, app),
         AppView::Dashboard => draw_dashboard(f, app),
     }
 }
 
-fn draw_home(f: &mut Frame) {
+fn draw_home(f: &mut Frame, app: &App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
@@ -33,7 +39,7 @@ fn draw_home(f: &mut Frame) {
 
     // 1. ASCII Art Logo (Professional Slant Style)
     // Font: ANSI Shadow / Slant
-    let logo_text = vec![
+    let logo_text = [
         "██████╗ ██╗██████╗ ███████╗██████╗ ",
         "██╔══██╗██║██╔══██╗██╔════╝██╔══██╗",
         "██████╔╝██║██████╔╝█████╗  ██████╔╝",
@@ -59,8 +65,6 @@ fn draw_home(f: &mut Frame) {
              Span::raw(" Find compressible artifacts"),
         ]),
         Line::from(""), // Spacer
-        /* 
-        // Disabled for v1.0
         Line::from(vec![
             Span::styled("2. Analytics ", Style::default().fg(Color::White)),
              Span::raw(" Visualize storage efficiency"),
@@ -68,9 +72,14 @@ fn draw_home(f: &mut Frame) {
         Line::from(""),
         Line::from(vec![
              Span::styled("3. Status    ", Style::default().fg(Color::White)),
-            Span::raw(" System health monitor"),
+            Span::raw(" Worker and engine settings"),
         ]),
-        */
+        Line::from(""),
+        Line::from(vec![
+             Span::styled("4. Problems  ", Style::default().fg(Color::White)),
+            Span::raw(" Errors and warnings this session"),
+        ]),
+        Line::from(""),
         Line::from(vec![
              Span::styled("Q. Quit      ", Style::default().fg(Color::Red)),
             Span::raw(" Exit Application"),
@@ -83,11 +92,25 @@ fn draw_home(f: &mut Frame) {
     // To vertically center the menu, we can use a helper or just render to the middle chunk
     f.render_widget(menu_p, chunks[1]);
 
-    // 3. Footer
-    let footer_text = " [1] Select | [Q] Quit ";
-    let footer = Paragraph::new(footer_text)
-        .alignment(Alignment::Center)
-        .style(Style::default().fg(Color::DarkGray));
+    // 3. Recent scan roots, then the footer
+    let mut footer_lines: Vec<Line> = Vec::new();
+    if !app.recent.scans.is_empty() {
+        footer_lines.push(Line::styled("Recent scans", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
+        for (key, scan) in ('A'..).zip(&app.recent.scans) {
+            let when = chrono::DateTime::from_timestamp(scan.last_scanned, 0)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            footer_lines.push(Line::from(vec![
+                Span::styled(format!("{}. ", key), Style::default().fg(Color::White)),
+                Span::raw(scan.path.display().to_string()),
+                Span::styled(format!("  {}", when), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+        footer_lines.push(Line::from(""));
+    }
+    footer_lines.push(Line::styled(" [1] Scan | [2] Analytics | [3] Status | [4] Problems | [Q] Quit ", Style::default().fg(Color::DarkGray)));
+    let footer = Paragraph::new(footer_lines)
+        .alignment(Alignment::Center);
     f.render_widget(footer, chunks[2]);
 }
 
@@ -97,7 +120,7 @@ fn draw_dashboard(f: &mut Frame, app: &mut App) {
         .constraints(
             [
                 Constraint::Length(1), // Minimal Header
-                // Constraint::Length(3), // Tabs - Removed for single view
+                Constraint::Length(1), // Tabs
                 Constraint::Min(0),    // Main Content
                 Constraint::Length(1), // Footer/Status Bar
             ]
@@ -106,145 +129,654 @@ fn draw_dashboard(f: &mut Frame, app: &mut App) {
         .split(f.area());
 
     draw_minimal_header(f, app, chunks[0]);
-    // draw_tabs(f, app, chunks[1]);
+    draw_tabs(f, app, chunks[1]);
     
-    // Direct render since only one tab
-    draw_file_list(f, app, chunks[1]);
+    match app.current_tab {
+        AppTab::Scanner => draw_file_list(f, app, chunks[2]),
+        AppTab::Analytics => draw_analytics(f, app, chunks[2]),
+        AppTab::Status => draw_status(f, app, chunks[2]),
+        AppTab::Problems => draw_problems(f, app, chunks[2]),
+    }
     
-    draw_footer(f, app, chunks[2]);
+    draw_footer(f, app, chunks[3]);
 
     if app.show_details {
         draw_details_popup(f, app);
     }
+
+    if app.preview.is_some() {
+        draw_preview_popup(f, app);
+    }
+
+    if app.pending_restore.is_some() {
+        draw_restore_prompt(f, app);
+    }
+
+    if app.pending_purge.is_some() {
+        draw_purge_prompt(f, app);
+    }
+
+    if app.compression_summary.is_some() {
+        draw_compression_summary(f, app);
+    }
+
+    if app.confirm_broad_scan {
+        draw_broad_scan_prompt(f, app);
+    }
+
+    if app.show_level_picker {
+        draw_level_picker(f, app);
+    }
 }
 
 fn draw_minimal_header(f: &mut Frame, app: &App, area: Rect) {
     let score = app.weissman_score;
-    let label = format!(" PIPER v1.0 | Weissman Score: {:.2} ", score);
-    let p = Paragraph::new(label)
+    let sort = match app.sort_order {
+        SortOrder::Size => "size",
+        SortOrder::Age => "age",
+    };
+    let label = format!(" PIPER v1.0 | Weissman Score: {:.2} | Level {} | Sort: {} ", score, app.compression_level, sort);
+    let mut spans = vec![Span::raw(label)];
+
+    if let Some(profile) = app.profile {
+        spans.push(Span::styled(format!("| Profile: {} ", profile.name()), Style::default().fg(Color::Cyan)));
+    }
+
+    if app.safe_mode {
+        spans.push(Span::styled(" SAFE MODE ", Style::default().fg(Color::Black).bg(Color::Green)));
+        spans.push(Span::raw(" "));
+    }
+
+    if !app.items.is_empty() {
+        spans.push(Span::styled(
+            format!("| On disk: {} (saved {}) ", format_size(app.current_footprint()), format_size(app.total_savings)),
+            Style::default().fg(Color::White),
+        ));
+    }
+
+    if app.projected_savings > 0 {
+        spans.push(Span::styled(
+            format!("| Potential savings: ~{} ", format_size(app.projected_savings)),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if let Some((visited, elapsed)) = app.scan_stats {
+        let secs = elapsed.as_secs_f64();
+        let rate = if secs > 0.0 { format!(" ({}/s)", format_count((visited as f64 / secs) as u64)) } else { String::new() };
+        spans.push(Span::styled(
+            format!("| Scanned {} files in {:.1}s{} ", format_count(visited), secs, rate),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    // Results may be incomplete if parts of the tree were unreadable
+    if !app.scan_warnings.is_empty() {
+        spans.push(Span::styled(
+            format!("| ⚠ {} ", app.scan_warnings.join(", ")),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    let p = Paragraph::new(Line::from(spans))
         .style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD).bg(Color::Black));
     f.render_widget(p, area);
 }
 
-/*
 fn draw_tabs(f: &mut Frame, app: &App, area: Rect) {
-    let titles = vec![" Scanner ", " Analytics "];
+    let problems = if app.problems.is_empty() {
+        " Problems ".to_string()
+    } else {
+        format!(" Problems ({}) ", app.problems.len())
+    };
+    let titles = vec![" Scanner ".to_string(), " Analytics ".to_string(), " Status ".to_string(), problems];
     let tabs = Tabs::new(titles)
         .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
         .divider(" | ")
         .select(match app.current_tab {
             AppTab::Scanner => 0,
             AppTab::Analytics => 1,
+            AppTab::Status => 2,
+            AppTab::Problems => 3,
         });
     f.render_widget(tabs, area);
 }
 
+fn draw_status(f: &mut Frame, app: &App, area: Rect) {
+    let label = Style::default().fg(Color::DarkGray);
+    let value = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+
+    let source = app.worker_limit();
+
+    let last_scan = if app.is_scanning {
+        format!("in progress ({})", app.scan_path.display())
+    } else if app.has_scanned {
+        format!("{} item{} in {}", app.items.len(), if app.items.len() == 1 { "" } else { "s" }, app.scan_path.display())
+    } else {
+        "not run yet; press [S] on the Scanner tab".to_string()
+    };
+
+    let text = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("   Last scan:       ", label),
+            Span::styled(last_scan, value),
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("   Worker threads:  ", label),
+            Span::styled(format!("{} ", app.worker_threads()), value),
+            Span::styled(format!("({})", source), label),
+        ]),
+        Line::from(Span::styled("   Compress/restore use their own pool, so RAYON_NUM_THREADS only affects scanning.", label)),
+        Line::from(Span::styled("   Each job runs one single-threaded zstd encoder.", label)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("   Adaptive levels: ", label),
+            Span::styled(if app.adaptive_levels { "on" } else { "off" }, value),
+            Span::styled(format!(" ({} per-extension override{})", app.level_overrides.len(),
+                if app.level_overrides.len() == 1 { "" } else { "s" }), label),
+        ]),
+        Line::from(vec![
+            Span::styled("   Keep originals:  ", label),
+            Span::styled(
+                if app.safe_mode { "yes (safe mode)" } else if app.keep_original { "yes" } else { "no" },
+                value,
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("   Symlinks:        ", label),
+            Span::styled(if app.dereference_symlinks { "dereferenced" } else { "stored as links" }, value),
+        ]),
+        Line::from(vec![
+            Span::styled("   Output dir:      ", label),
+            Span::styled(
+                app.output_dir.as_ref().map_or("next to sources".to_string(), |d| d.display().to_string()),
+                value,
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("   Backups:         ", label),
+            Span::styled(
+                app.backup_dir.as_ref().map_or("off".to_string(), |d| d.display().to_string()),
+                value,
+            ),
+        ]),
+    ];
+    f.render_widget(Paragraph::new(text), area);
+}
+
+/// Every error and warning this session, newest first, with the underlying
+/// error message the Scanner row has no room for.
+fn draw_problems(f: &mut Frame, app: &App, area: Rect) {
+    if app.problems.is_empty() {
+        let text = Paragraph::new(vec![
+            Line::from(""),
+            Line::from(""),
+            Line::styled("   No errors or warnings this session.", Style::default().fg(Color::DarkGray)),
+        ]);
+        f.render_widget(text, area);
+        return;
+    }
+
+    let rows = app.problems.iter().rev().map(|problem| {
+        let (icon, color) = match (problem.severity, app.ascii_mode) {
+            (Severity::Error, false) => ("❌", Color::Red),
+            (Severity::Error, true) => ("[x]", Color::Red),
+            (Severity::Warning, false) => ("⚠", Color::Yellow),
+            (Severity::Warning, true) => ("[!]", Color::Yellow),
+        };
+        Row::new(vec![
+            Cell::from(icon),
+            Cell::from(problem.path.display().to_string()),
+            Cell::from(problem.reason.clone()),
+            Cell::from(problem.message.clone().unwrap_or_default()),
+        ])
+        .style(Style::default().fg(color))
+    });
+
+    let table = Table::new(rows, [
+            Constraint::Length(4),
+            Constraint::Percentage(40),
+            Constraint::Percentage(25),
+            Constraint::Percentage(35),
+        ])
+        .header(Row::new(vec!["", "Path", "Reason", "Error"]).style(Style::default().fg(Color::DarkGray)))
+        .block(Block::default().borders(Borders::ALL).title(" Errors and warnings, newest first "));
+    f.render_widget(table, area);
+}
+
 fn draw_analytics(f: &mut Frame, app: &App, area: Rect) {
-    let text = Paragraph::new("\n\n   Analytics Module Coming Soon...")
-        .style(Style::default().fg(Color::DarkGray));
-    f.render_widget(text, area);
+    let history: Vec<_> = app.history.runs().collect();
+    let location = match app.history.get_path() {
+        Some(path) => format!("   Kept in {} ([O]pen folder)", path.display()),
+        None => "   Not saved: no history location".to_string(),
+    };
+    let location = Line::styled(location, Style::default().fg(Color::DarkGray));
+
+    // Dry runs, listed apart so they can be held against the real runs
+    let projections: Vec<_> = app.history.projections().collect();
+    let projected = projections.last().map(|latest| Line::styled(
+        format!(
+            "   Projections: {} dry run{}, latest ~{} over {} item{} (not in the total)",
+            projections.len(), if projections.len() == 1 { "" } else { "s" },
+            format_size(latest.bytes_saved), latest.items, if latest.items == 1 { "" } else { "s" },
+        ),
+        Style::default().fg(Color::Cyan),
+    ));
+
+    if history.is_empty() {
+        let mut lines = vec![
+            Line::from(""),
+            Line::from(""),
+            Line::styled("   No compression runs recorded yet. Savings show up here after [C]ompress.", Style::default().fg(Color::DarkGray)),
+        ];
+        lines.extend(projected);
+        lines.push(location);
+        f.render_widget(Paragraph::new(lines), area);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3 + projected.is_some() as u16), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    let summary = format!(
+        "   All-time savings: {} over {} run{}",
+        format_size(app.history.total_saved()), history.len(), if history.len() == 1 { "" } else { "s" },
+    );
+    let summary = Line::styled(summary, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD));
+    let mut lines = vec![Line::from(""), summary];
+    lines.extend(projected);
+    lines.push(location);
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    // Most recent runs that fit, each labelled with the level it used
+    const BAR_WIDTH: u16 = 7;
+    let fits = (chunks[1].width.saturating_sub(2) / (BAR_WIDTH + 1)).max(1) as usize;
+    let shown = &history[history.len().saturating_sub(fits)..];
+    // One unit for the whole window, picked by its biggest run, so small runs
+    // still get a visible label; heights are scaled from the raw bytes
+    let (unit, unit_name) = size_unit(shown.iter().map(|e| e.bytes_saved).max().unwrap_or_default());
+    let bars: Vec<Bar> = shown.iter()
+        .map(|entry| {
+            let value = entry.bytes_saved as f64 / unit as f64;
+            let text = if value < 10.0 && unit > 1 { format!("{:.1}", value) } else { format!("{:.0}", value) };
+            let label = if entry.level > 0 { format!("L{}", entry.level) } else { "L?".to_string() };
+            Bar::default().value(entry.bytes_saved).text_value(text).label(Line::from(label))
+        })
+        .collect();
+
+    let chart = BarChart::new(bars)
+        .block(Block::default().title(format!(" Saved per run ({}) ", unit_name)).borders(Borders::ALL))
+        .bar_width(BAR_WIDTH)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+
+    if app.history.leaderboard.is_empty() {
+        f.render_widget(chart, chunks[1]);
+        return;
+    }
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+        .split(chunks[1]);
+    f.render_widget(chart, columns[0]);
+    draw_leaderboard(f, app, columns[1]);
+}
+
+/// Best artifacts ever compressed, by ratio or bytes saved (`r` switches).
+fn draw_leaderboard(f: &mut Frame, app: &App, area: Rect) {
+    let (top, title) = if app.leaderboard_by_ratio {
+        (app.history.top_by_ratio(analytics::LEADERBOARD_SIZE), " Leaderboard: best ratio ([R] by saved) ")
+    } else {
+        (app.history.top_by_saved(analytics::LEADERBOARD_SIZE), " Leaderboard: most saved ([R] by ratio) ")
+    };
+
+    let rows: Vec<Row> = top.iter().enumerate()
+        .map(|(rank, artifact)| {
+            let name = artifact.path.file_name().unwrap_or(artifact.path.as_os_str()).to_string_lossy().to_string();
+            Row::new(vec![
+                Cell::from(format!("{:>2}", rank + 1)),
+                Cell::from(name),
+                Cell::from(format!("{:.1}x", artifact.ratio())).style(Style::default().fg(Color::Green)),
+                Cell::from(format_size(artifact.saved())).style(Style::default().fg(Color::Cyan)),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(rows, [Constraint::Length(3), Constraint::Min(10), Constraint::Length(8), Constraint::Length(11)])
+        .header(Row::new(vec!["#", "Artifact", "Ratio", "Saved"]).style(Style::default().fg(Color::DarkGray)))
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(table, area);
 }
-*/
 
 
 
 fn draw_file_list(f: &mut Frame, app: &mut App, area: Rect) {
+    if let Some(err) = &app.scan_error {
+        let text = Paragraph::new(format!("\n   {}\n   Pass a valid directory with --scan.", err))
+            .style(Style::default().fg(Color::Red));
+        f.render_widget(text, area);
+        return;
+    }
+
     if app.items.is_empty() && !app.is_scanning {
-         let text = Paragraph::new("\n   No artifacts found. Press [S] to Scan.")
-            .style(Style::default().fg(Color::DarkGray));
-         f.render_widget(text, area);
-         return;
+        let hint = Style::default().fg(Color::DarkGray);
+        let path = Span::styled(app.scan_path.display().to_string(), Style::default().fg(Color::Cyan));
+        let mut lines = vec![Line::from("")];
+        if app.has_scanned {
+            lines.push(Line::from(vec![Span::styled("   No artifacts found in ", hint), path]));
+            lines.push(Line::styled("   Press [S] to scan again, or widen the search with large_file_mb in the config.", hint));
+            for warning in &app.scan_warnings {
+                lines.push(Line::styled(format!("   {}", warning), Style::default().fg(Color::Yellow)));
+            }
+        } else {
+            lines.push(Line::from(vec![Span::styled("   Press [S] to scan ", hint), path]));
+            lines.push(Line::styled("   Pick another root with --scan or `path` under [scan] in the config.", hint));
+        }
+        f.render_widget(Paragraph::new(lines), area);
+        return;
     }
 
     if app.is_scanning {
-        let spinner = match app.spinner_state {
-             0 => "⠋", 1 => "⠙", 2 => "⠹", 3 => "⠸", _ => "⠼",
-         };
-        let text = format!("\n   {} Scanning directory...", spinner);
+        let text = if let Some(path) = &app.resizing {
+            format!("\n   {} Measuring {}...", spinner_frame(app), path.display())
+        } else if app.refresh.is_some() {
+            format!("\n   {} Refreshing: rescanning {} with the same settings...", spinner_frame(app), app.scan_path.display())
+        } else {
+            format!("\n   {} Scanning directory...", spinner_frame(app))
+        };
         let p = Paragraph::new(text).style(Style::default().fg(Color::Yellow));
         f.render_widget(p, area);
         return;
     } 
     
     if app.is_compressing {
-         let spinner = match app.spinner_state {
-             0 => "⠋", 1 => "⠙", 2 => "⠹", 3 => "⠸", _ => "⠼",
-         };
-         let text = format!("\n   {} Compressing artifacts... Please wait.", spinner);
-         let p = Paragraph::new(text).style(Style::default().fg(Color::Cyan));
-        f.render_widget(p, area);
+        draw_batch_progress(f, app, area);
         return;
     }
 
-    let rows: Vec<Row> = app.items.iter().map(|i| {
-        let status_icon = match i.status {
-            FileStatus::Found => "📦",
-            FileStatus::Compressing => "🔄",
-            FileStatus::Done => "✅",
-            FileStatus::Error => "❌",
-            FileStatus::Deleted => "🗑️ ",
-            FileStatus::Restored => "↩ ",
-        };
+    // Only build the rows that fit: with tens of thousands of candidates,
+    // formatting every one per frame is most of the frame time
+    let all_rows = app.rows();
+    if all_rows.is_empty() && app.hide_completed {
+        let text = Paragraph::new("\n   Everything here is done. Press [H] to show completed items.")
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(text, area);
+        return;
+    }
+    let window = visible_window(
+        all_rows.len(),
+        app.list_state.selected(),
+        app.list_state.offset(),
+        area.height.saturating_sub(2) as usize, // Header and its margin
+    );
 
+    let visual = app.visual_range();
+    let rows: Vec<Row> = all_rows[window.clone()].iter().cloned().zip(window.clone()).map(|(row, idx)| {
+        let i = match row {
+            ListRow::Item(i) => &app.items[i],
+            ListRow::Project(root, count, size, collapsed) => return project_row(app, root, count, size, collapsed),
+        };
         let style = if i.status == FileStatus::Deleted {
             Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT)
+        } else if i.status == FileStatus::Skipped {
+            Style::default().fg(Color::DarkGray)
         } else if i.status == FileStatus::Done {
             Style::default().fg(Color::Green)
         } else if i.status == FileStatus::Error {
             Style::default().fg(Color::Red)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(age_color(i.age_days))
         };
 
-        let reason_style = Style::default().fg(Color::DarkGray); // Muted reason
-
-        let size_str = if let Some(comp) = i.compressed_size {
-             format!("{} -> {}", format_size(i.original_size), format_size(comp))
-        } else if i.status == FileStatus::Deleted {
-            format!("{} -> 0", format_size(i.original_size))
+        // Once processed, the reason is a status detail rather than the category
+        let reason_style = if i.status == FileStatus::Found {
+            Style::default().fg(category_color(i.category))
         } else {
-            format_size(i.original_size)
+            Style::default().fg(Color::DarkGray)
         };
 
         let check = if i.selected { " [x]" } else { " [ ]" };
-        let path_str = format!("{}{}", check, i.path);
+        let dash = if app.ascii_mode { "-" } else { "—" };
 
-        Row::new(vec![
-            Cell::from(status_icon),
-            Cell::from(path_str).style(style),
-            Cell::from(i.reason.clone()).style(reason_style),
-            Cell::from(size_str).style(Style::default().fg(Color::Cyan)),
-        ])
+        // Pending visual range, not yet committed to `selected`
+        let row_style = if visual.as_ref().is_some_and(|r| r.contains(&idx)) {
+            Style::default().bg(Color::DarkGray)
+        } else {
+            Style::default()
+        };
+
+        let cells: Vec<Cell> = app.visible_columns().iter().map(|&column| match column {
+            Column::Status => Cell::from(status_icon(i, app.ascii_mode)),
+            Column::Path => Cell::from(format!("{}{}", check, i.path.display())).style(style),
+            Column::Relative => Cell::from(format!("{}{}", check, relative_path(app, i).display())).style(style),
+            Column::Type => Cell::from(i.reason.clone()).style(reason_style),
+            Column::Size => {
+                let size_str = if let Some(comp) = i.compressed_size {
+                    format!("{} -> {}", format_size(i.original_size), format_size(comp))
+                } else if i.status == FileStatus::Deleted {
+                    format!("{} -> 0", format_size(i.original_size))
+                } else {
+                    format_size(i.original_size)
+                };
+                Cell::from(size_str).style(Style::default().fg(Color::Cyan))
+            }
+            // Only meaningful once something actually got smaller
+            Column::Ratio => Cell::from(match i.compressed_size {
+                Some(comp) if i.status == FileStatus::Done && comp > 0 => {
+                    format!("{:.1}x", i.original_size as f64 / comp as f64)
+                }
+                _ => dash.to_string(),
+            }).style(Style::default().fg(Color::Green)),
+            Column::Age => Cell::from(match i.age_days {
+                Some(days) => format!("{}d", days),
+                None => dash.to_string(),
+            }).style(Style::default().fg(age_color(i.age_days))),
+            Column::Files => Cell::from(i.file_count.map_or_else(|| dash.to_string(), format_count))
+                .style(Style::default().fg(Color::DarkGray)),
+        }).collect();
+        Row::new(cells).style(row_style)
     }).collect();
 
-    let table = Table::new(rows, [
-            Constraint::Length(3),
-            Constraint::Percentage(50), 
-            Constraint::Percentage(25), 
-            Constraint::Percentage(22)
-        ])
+    let columns = app.visible_columns();
+    let constraints: Vec<Constraint> = columns.iter().map(|&c| column_width(c)).collect();
+    let headers: Vec<&str> = columns.iter().map(|&c| column_header(c)).collect();
+
+    let table = Table::new(rows, constraints)
         .header(
-            Row::new(vec!["", " Artifact", " Type", " Size"])
+            Row::new(headers)
                 .style(Style::default().fg(Color::DarkGray))
                 .bottom_margin(1)
         )
         // No borders for cleaner look
         .highlight_symbol(" > ");
 
-    f.render_stateful_widget(table, area, &mut app.list_state);
+    // The table only sees the window, so hand it a selection relative to that
+    let mut state = TableState::default()
+        .with_selected(app.list_state.selected().filter(|s| window.contains(s)).map(|s| s - window.start));
+    f.render_stateful_widget(table, area, &mut state);
+    *app.list_state.offset_mut() = window.start;
+}
+
+/// Rows `height` tall from `offset`, scrolled just enough to show `selected`,
+/// the same way ratatui scrolls a full table.
+fn visible_window(total: usize, selected: Option<usize>, offset: usize, height: usize) -> std::ops::Range<usize> {
+    let height = height.max(1);
+    let mut start = offset.min(total.saturating_sub(height));
+    if let Some(selected) = selected.map(|s| s.min(total.saturating_sub(1))) {
+        if selected < start {
+            start = selected;
+        } else if selected >= start + height {
+            start = selected + 1 - height;
+        }
+    }
+    start..(start + height).min(total)
 }
 
-fn draw_footer(f: &mut Frame, _app: &App, area: Rect) {
-    // Minimal status line, vim-like
-    let instructions = Paragraph::new(" NORMAL MODE | [S]can [C]ompress [D]elete [E]restore [Q]uit [Space]Select")
+/// Section header in the grouped view: fold marker, project path, item count and total size.
+fn project_row(app: &App, root: Option<PathBuf>, count: usize, size: u64, collapsed: bool) -> Row<'static> {
+    let marker = match (collapsed, app.ascii_mode) {
+        (true, true) => " +",
+        (false, true) => " -",
+        (true, false) => " ▸",
+        (false, false) => " ▾",
+    };
+    let name = match &root {
+        Some(root) => root.strip_prefix(&app.scan_path).ok()
+            .filter(|rel| !rel.as_os_str().is_empty())
+            .unwrap_or(root)
+            .display()
+            .to_string(),
+        None => "(no project)".to_string(),
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let cells: Vec<Cell> = app.visible_columns().iter().map(|&column| match column {
+        Column::Status => Cell::from(marker),
+        Column::Path | Column::Relative => Cell::from(format!(" {}", name)).style(bold.fg(Color::White)),
+        Column::Type => Cell::from(format!("{} item{}", count, if count == 1 { "" } else { "s" })).style(Style::default().fg(Color::DarkGray)),
+        Column::Size => Cell::from(format_size(size)).style(bold.fg(Color::Cyan)),
+        Column::Ratio | Column::Age | Column::Files => Cell::from(""),
+    }).collect();
+    Row::new(cells)
+}
+
+/// Fixed widths for short values; the paths and reasons share what's left.
+fn column_width(column: Column) -> Constraint {
+    match column {
+        Column::Status => Constraint::Length(4),
+        Column::Path | Column::Relative => Constraint::Fill(2),
+        Column::Type => Constraint::Fill(1),
+        Column::Size => Constraint::Percentage(22),
+        Column::Ratio | Column::Files => Constraint::Length(8),
+        Column::Age => Constraint::Length(6),
+    }
+}
+
+fn column_header(column: Column) -> &'static str {
+    match column {
+        Column::Status => "",
+        Column::Path | Column::Relative => " Artifact",
+        Column::Type => " Type",
+        Column::Size => " Size",
+        Column::Ratio => " Ratio",
+        Column::Age => " Age",
+        Column::Files => " Files",
+    }
+}
+
+/// The item's path from its project root, or else from the scan root.
+fn relative_path<'a>(app: &App, item: &'a FileItem) -> &'a Path {
+    item.project.iter().map(PathBuf::as_path).chain([app.scan_path.as_path()])
+        .find_map(|root| item.path.strip_prefix(root).ok().filter(|rel| !rel.as_os_str().is_empty()))
+        .unwrap_or(&item.path)
+}
+
+/// Older items get warmer colors so ancient logs stand out.
+fn age_color(age_days: Option<u64>) -> Color {
+    match age_days {
+        Some(d) if d >= 365 => Color::Magenta,
+        Some(d) if d >= 180 => Color::LightRed,
+        Some(d) if d >= 90 => Color::Yellow,
+        _ => Color::White,
+    }
+}
+
+/// One gauge for the whole batch against its precomputed total, then each
+/// item's own share, in the order they were queued.
+fn draw_batch_progress(f: &mut Frame, app: &App, area: Rect) {
+    let (done, total) = app.compress_progress();
+    let count = app.compress_batch.len();
+    let text = format!(
+        "\n   {} Compressing {} item{}... {} of {}",
+        spinner_frame(app), count, if count == 1 { "" } else { "s" }, format_size(done.min(total)), format_size(total),
+    );
+    f.render_widget(Paragraph::new(text).style(Style::default().fg(Color::Cyan)), area);
+
+    let gauge_row = |y: u16| Rect { x: area.x + 3, y: area.y + y, width: area.width.saturating_sub(6).min(60), height: 1 };
+    let ratio = |done: u64, total: u64| if total == 0 { 1.0 } else { done.min(total) as f64 / total as f64 };
+    if area.height > 3 {
+        let batch = ratio(done, total);
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Cyan))
+            .ratio(batch)
+            .label(format!("{:.0}% of the batch", batch * 100.0));
+        f.render_widget(gauge, gauge_row(3));
+    }
+    // Large folders spend most of their time in the tar walk; show how far along it is
+    if let Some((added, total)) = app.archive_progress()
+        && area.height > 4
+    {
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Cyan))
+            .ratio(ratio(added, total))
+            .label(format!("{} of {} files archived", added, total));
+        f.render_widget(gauge, gauge_row(4));
+    }
+
+    let list_area = Rect { y: area.y + 6, height: area.height.saturating_sub(6), ..area };
+    let lines: Vec<Line> = app.compress_batch.iter()
+        .take(list_area.height as usize)
+        .filter_map(|&idx| {
+            let item = app.items.get(idx)?;
+            let (done, size) = app.item_progress(idx);
+            let style = match item.status {
+                FileStatus::Compressing if done > 0 => Style::default().fg(Color::Cyan),
+                FileStatus::Compressing => Style::default().fg(Color::DarkGray),
+                FileStatus::Error => Style::default().fg(Color::Red),
+                _ => Style::default().fg(Color::Green),
+            };
+            Some(Line::styled(
+                format!("   {} {:>4.0}%  {:>10}  {}", status_icon(item, app.ascii_mode), ratio(done, size) * 100.0,
+                    format_size(size), item.path.display()),
+                style,
+            ))
+        })
+        .collect();
+    if list_area.height > 0 {
+        f.render_widget(Paragraph::new(lines), list_area);
+    }
+}
+
+fn draw_footer(f: &mut Frame, app: &App, area: Rect) {
+    if let Some(notice) = &app.notice {
+        let p = Paragraph::new(format!(" {}", notice))
+            .style(Style::default().fg(Color::Black).bg(Color::Yellow));
+        f.render_widget(p, area);
+        return;
+    }
+
+    // Minimal status line, vim-like; the mode segment doubles as the selection total
+    let (count, size) = app.selection_summary();
+    let mode = if let Some(range) = app.visual_range() {
+        Span::styled(
+            format!(" VISUAL {} rows | [J/K]Extend [Space]Select [Esc]Cancel ", range.count()),
+            Style::default().fg(Color::Black).bg(Color::Magenta).add_modifier(Modifier::BOLD),
+        )
+    } else if count > 0 {
+        Span::styled(
+            format!(" Selected: {} item{}, {} ", count, if count == 1 { "" } else { "s" }, format_size(size)),
+            Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::raw(" NORMAL MODE ")
+    };
+    let keys = if app.visual_anchor.is_some() {
+        Span::raw("")
+    } else if let Some((message, _)) = &app.status_message {
+        Span::styled(format!("| {}", message), Style::default().fg(Color::Black).add_modifier(Modifier::BOLD))
+    } else { Span::raw("| [S]can [R]efresh [C]ompress [T]op 10 [+/-]Level [D]elete [Shift-D] forever [E]restore [U]ndo [X]pand [F] size [P]review [A]ge sort [G]roup [H]ide done [M]ode [O]pen [Y]ank path [Tab]Tabs [Q]uit [Space]Select [Shift-A]ll [V]isual") };
+    let instructions = Paragraph::new(Line::from(vec![mode, keys]))
         .style(Style::default().fg(Color::Black).bg(Color::Cyan));
     f.render_widget(instructions, area);
 }
 
 fn draw_details_popup(f: &mut Frame, app: &App) {
-    if let Some(i) = app.list_state.selected() {
-        if i >= app.items.len() { return; }
-        
+    if let Some(i) = app.selected_item() {
         let item = &app.items[i];
         
         let block = Block::default().title(" Details ").borders(Borders::ALL)
@@ -261,28 +793,42 @@ fn draw_details_popup(f: &mut Frame, app: &App) {
                 [
                     Constraint::Length(1), // Path
                     Constraint::Length(1), // Sort Reason
+                    Constraint::Length(1), // Files
                     Constraint::Length(1), // Original
                     Constraint::Length(1), // Compressed
                     Constraint::Length(1), // Spacer
                     Constraint::Length(1), // Savings
+                    Constraint::Length(1), // Best backend
                 ]
                 .as_ref(),
             )
             .split(area);
 
-        f.render_widget(Paragraph::new(format!("Path: {}", item.path)).style(Style::default().fg(Color::Yellow)), chunks[0]);
-        f.render_widget(Paragraph::new(format!("Type: {}", item.reason)).style(Style::default().fg(Color::DarkGray)), chunks[1]);
-        f.render_widget(Paragraph::new(format!("Original:   {}", format_size(item.original_size))), chunks[2]);
+        f.render_widget(Paragraph::new(format!("Path: {}", item.path.display())).style(Style::default().fg(Color::Yellow)), chunks[0]);
+        let reason = if item.status == FileStatus::Error {
+            Paragraph::new(format!("Error: {}", item.reason)).style(Style::default().fg(Color::Red))
+        } else {
+            Paragraph::new(format!("Type: {}", item.reason)).style(Style::default().fg(Color::DarkGray))
+        };
+        f.render_widget(reason, chunks[1]);
+        let files_str = match item.file_count {
+            Some(n) => format!("Files:      {}", n),
+            None => "Files:      1".to_string(),
+        };
+        f.render_widget(Paragraph::new(files_str), chunks[2]);
+        f.render_widget(Paragraph::new(format!("Original:   {}", format_size(item.original_size))), chunks[3]);
         
         let compressed_str = if let Some(s) = item.compressed_size {
-            format!("{}", format_size(s))
+            format_size(s)
         } else {
             "Pending".to_string()
         };
-        f.render_widget(Paragraph::new(format!("Compressed: {}", compressed_str)), chunks[3]);
+        f.render_widget(Paragraph::new(format!("Compressed: {}", compressed_str)), chunks[4]);
 
-        let savings = if item.status == FileStatus::Error {
+        let savings = if item.status == FileStatus::Error && item.compressed_size.is_some() {
             "Savings:    Failed (Incompressible)".to_string()
+        } else if item.status == FileStatus::Error {
+            "Savings:    Failed".to_string()
         } else if let Some(s) = item.compressed_size {
             if item.original_size > s {
                 let diff = item.original_size - s;
@@ -294,23 +840,232 @@ fn draw_details_popup(f: &mut Frame, app: &App) {
         } else {
              "Savings:    Pending...".to_string()
         };
-        f.render_widget(Paragraph::new(savings).style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)), chunks[5]);
+        f.render_widget(Paragraph::new(savings).style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)), chunks[6]);
+
+        let estimate = app.backend_estimate.as_ref().filter(|(row, _)| *row == i).map(|(_, e)| e);
+        let best = match estimate {
+            Some(Ok((backend, sample))) if sample.original_size > 0 => {
+                let pct = sample.original_size.saturating_sub(sample.compressed_size) as f64 / sample.original_size as f64 * 100.0;
+                format!("Best:       {} (~{:.0}% smaller on a {} sample)", backend.name(), pct, format_size(sample.original_size))
+            }
+            Some(Err(e)) => format!("Best:       Unknown ({})", e),
+            _ => String::new(),
+        };
+        f.render_widget(Paragraph::new(best).style(Style::default().fg(Color::DarkGray)), chunks[7]);
     }
 }
 
-fn format_size(size: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if size >= GB {
-        format!("{:.2} GB", size as f64 / GB as f64)
-    } else if size >= MB {
-        format!("{:.2} MB", size as f64 / MB as f64)
-    } else if size >= KB {
-        format!("{:.2} KB", size as f64 / KB as f64)
-    } else {
-        format!("{} B", size)
+fn draw_level_picker(f: &mut Frame, app: &App) {
+    let area = centered_rect(50, 30, f.area());
+    f.render_widget(ratatui::widgets::Clear, area); // Clear background
+
+    let block = Block::default().title(" Compress ").borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let (count, size, reclaimable) = app.compression_plan();
+
+    let text = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::raw("Level:  "),
+            Span::styled(format!("< {} >", app.compression_level), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        ]),
+        Line::from(Span::styled(
+            format!("(zstd {}-{})", compressor::MIN_LEVEL, compressor::MAX_LEVEL),
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(""),
+        Line::from(format!("About to process {} item{} ({}), ~{} reclaimable", count, if count == 1 { "" } else { "s" },
+            format_size(size), format_size(reclaimable))),
+        Line::from(Span::styled(format!("in {}", app.scan_path.display()), Style::default().fg(Color::DarkGray))),
+        Line::from(""),
+        Line::from(Span::styled("[+/-] Adjust  [Enter] Compress  [Esc] Cancel", Style::default().fg(Color::DarkGray))),
+    ];
+
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center).block(block), area);
+}
+
+fn draw_broad_scan_prompt(f: &mut Frame, app: &App) {
+    let area = centered_rect(50, 25, f.area());
+    f.render_widget(ratatui::widgets::Clear, area); // Clear background
+
+    let block = Block::default().title(" Scan ").borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let text = vec![
+        Line::from(""),
+        Line::from(format!("{} is a very broad scan root.", app.scan_path.display())),
+        Line::from("The scan may take a long time and flag system files."),
+        Line::from(""),
+        Line::from(Span::styled("[Y] Scan anyway  [Esc] Cancel", Style::default().fg(Color::DarkGray))),
+    ];
+
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center).block(block), area);
+}
+
+fn draw_restore_prompt(f: &mut Frame, app: &App) {
+    let Some((targets, moved)) = &app.pending_restore else { return; };
+
+    let area = centered_rect(50, 25, f.area());
+    f.render_widget(ratatui::widgets::Clear, area); // Clear background
+
+    let block = Block::default().title(" Restore ").borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let text = vec![
+        Line::from(""),
+        Line::from(format!("{} of {} archive{} moved since compressing.", moved, targets.len(),
+            if targets.len() == 1 { " was" } else { "s were" })),
+        Line::from("Restore to the original location (recreating folders)?"),
+        Line::from(""),
+        Line::from(Span::styled("[Y] Original location  [N] Next to archive  [Esc] Cancel", Style::default().fg(Color::DarkGray))),
+    ];
+
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center).block(block), area);
+}
+
+fn draw_purge_prompt(f: &mut Frame, app: &App) {
+    let Some(targets) = &app.pending_purge else { return; };
+    let total: u64 = targets.iter().filter_map(|&i| app.items.get(i)).map(|i| i.original_size).sum();
+
+    let area = centered_rect(50, 25, f.area());
+    f.render_widget(ratatui::widgets::Clear, area); // Clear background
+
+    let block = Block::default().title(" Delete Permanently ").borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+
+    let text = vec![
+        Line::from(""),
+        Line::from(format!("Delete {} item{} ({}) permanently?", targets.len(),
+            if targets.len() == 1 { "" } else { "s" }, format_size(total))),
+        Line::from(Span::styled("This cannot be undone: nothing goes to the trash or backups.",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled("[Shift-Y] Delete forever  [Any other key] Cancel", Style::default().fg(Color::DarkGray))),
+    ];
+
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center).block(block), area);
+}
+
+fn draw_compression_summary(f: &mut Frame, app: &App) {
+    let Some(summary) = &app.compression_summary else { return; };
+
+    let area = centered_rect(50, 25, f.area());
+    f.render_widget(ratatui::widgets::Clear, area); // Clear background
+
+    // Red when something failed, so it isn't missed among the done rows
+    let color = if summary.errors > 0 { Color::Red } else { Color::Green };
+    let block = Block::default().title(" Compression Finished ").borders(Borders::ALL)
+        .border_style(Style::default().fg(color));
+
+    let text = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(format!("{} done", summary.done), Style::default().fg(Color::Green)),
+            Span::raw("  "),
+            Span::styled(format!("{} skipped", summary.skipped), Style::default().fg(Color::Yellow)),
+            Span::raw("  "),
+            Span::styled(format!("{} failed", summary.errors), Style::default().fg(Color::Red)),
+        ]),
+        Line::from(format!("Saved {} in {:.1}s", format_size(summary.saved), summary.elapsed.as_secs_f64())),
+        Line::from(""),
+        Line::from(Span::styled("[Any key] Close", Style::default().fg(Color::DarkGray))),
+    ];
+
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center).block(block), area);
+}
+
+fn draw_preview_popup(f: &mut Frame, app: &App) {
+    let Some(preview) = &app.preview else { return; };
+
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(ratatui::widgets::Clear, area); // Clear background
+
+    let (title, lines) = match preview {
+        Ok(entries) => {
+            let total: u64 = entries.iter().map(|(_, size)| size).sum();
+            let title = format!(" Archive Contents: {} entries, {} [Esc] Close ", entries.len(), format_size(total));
+            let lines: Vec<Line> = entries.iter().map(|(name, size)| {
+                Line::from(vec![
+                    Span::styled(format!("{:>10}  ", format_size(*size)), Style::default().fg(Color::Cyan)),
+                    Span::raw(name.clone()),
+                ])
+            }).collect();
+            (title, lines)
+        }
+        Err(e) => (
+            " Archive Contents [Esc] Close ".to_string(),
+            vec![Line::from(Span::styled(format!("Could not read archive: {}", e), Style::default().fg(Color::Red)))],
+        ),
+    };
+
+    let block = Block::default().title(title).borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Braille frames look best, but render as boxes without a UTF-8 locale.
+fn spinner_frame(app: &App) -> &'static str {
+    const UNICODE: [&str; 4] = ["⠋", "⠙", "⠹", "⠸"];
+    const ASCII: [&str; 4] = ["|", "/", "-", "\\"];
+    let frames = if app.ascii_mode { ASCII } else { UNICODE };
+    frames[app.spinner_state as usize % frames.len()]
+}
+
+/// Pending items show what they are; processed ones show what happened.
+fn status_icon(item: &FileItem, ascii: bool) -> &'static str {
+    match (&item.status, ascii) {
+        (FileStatus::Found, false) => match item.category {
+            Category::HeavyDir => "📦",
+            Category::StaleLog => "📜",
+            Category::LargeFile => "🗄️ ",
+            Category::BigFile => "🐘",
+            Category::Duplicate => "👯",
+            Category::Listed => "📋",
+            Category::AlreadyCompressedSkip => "🗜️ ",
+        },
+        (FileStatus::Compressing, false) => "🔄",
+        (FileStatus::Done, false) => "✅",
+        (FileStatus::Error, false) => "❌",
+        (FileStatus::Deleted, false) => "🗑️ ",
+        (FileStatus::Restored, false) => "↩ ",
+        (FileStatus::Skipped, false) => "⏭ ",
+        (FileStatus::Found, true) => match item.category {
+            Category::HeavyDir => "[D]",
+            Category::StaleLog => "[L]",
+            Category::LargeFile => "[F]",
+            Category::BigFile => "[B]",
+            Category::Duplicate => "[=]",
+            Category::Listed => "[P]",
+            Category::AlreadyCompressedSkip => "[z]",
+        },
+        (FileStatus::Compressing, true) => "[~]",
+        (FileStatus::Done, true) => "[ok]",
+        (FileStatus::Error, true) => "[x]",
+        (FileStatus::Deleted, true) => "[-]",
+        (FileStatus::Restored, true) => "[<]",
+        (FileStatus::Skipped, true) => "[>]",
+    }
+}
+
+/// Muted, but distinct enough to tell categories apart at a glance.
+fn category_color(category: Category) -> Color {
+    match category {
+        Category::HeavyDir => Color::Magenta,
+        Category::StaleLog => Color::Yellow,
+        Category::LargeFile => Color::Blue,
+        Category::BigFile => Color::LightBlue,
+        Category::Duplicate => Color::LightCyan,
+        Category::Listed => Color::White,
+        Category::AlreadyCompressedSkip => Color::DarkGray,
+    }
+}
+
+/// 1234 -> "1.2k", 1234567 -> "1.2M"; small counts as is.
+fn format_count(n: u64) -> String {
+    match n {
+        0..1_000 => n.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", n as f64 / 1e3),
+        _ => format!("{:.1}M", n as f64 / 1e6),
     }
 }
 
@@ -0,0 +1,26 @@
+//! Byte counts for people: the TUI and the plain-text flags print sizes the same way.
+
+/// 1536 -> "1.50 KB"; under a kilobyte, exact bytes.
+pub fn format_size(size: u64) -> String {
+    match size_unit(size) {
+        (1, _) => format!("{} B", size),
+        (unit, name) => format!("{:.2} {}", size as f64 / unit as f64, name),
+    }
+}
+
+/// The largest of B/KB/MB/GB that `size` is at least one of: (bytes per unit, name).
+pub fn size_unit(size: u64) -> (u64, &'static str) {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if size >= GB {
+        (GB, "GB")
+    } else if size >= MB {
+        (MB, "MB")
+    } else if size >= KB {
+        (KB, "KB")
+    } else {
+        (1, "B")
+    }
+}
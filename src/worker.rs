@@ -0,0 +1,157 @@
+//! Background jobs for the app. Scans, compression batches and restores run
+//! on a thread pool the worker owns, and whatever they report comes back
+//! through one channel that lives as long as the app, drained in `App::tick`.
+//! Jobs can overlap without one's results being dropped when the next starts.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+pub struct Worker<M> {
+    tx: Sender<M>,
+    rx: Receiver<M>,
+    pool: Option<ThreadPool>, // `None` if the OS refused the threads; jobs then get one each
+    running: Arc<AtomicUsize>, // Jobs that change files, for `shutdown` to wait on
+    stopping: Arc<AtomicBool>, // Set by `shutdown`
+}
+
+/// What a job gets: a way to report back, and whether to wind down.
+pub struct Job<M> {
+    tx: Sender<M>,
+    stopping: Arc<AtomicBool>,
+}
+
+// By hand: a derive would require `M: Clone`, and messages needn't be
+impl<M> Clone for Job<M> {
+    fn clone(&self) -> Self {
+        Job { tx: self.tx.clone(), stopping: self.stopping.clone() }
+    }
+}
+
+/// Counts a job out of `Worker::running` when it ends, even by panic.
+struct Running(Arc<AtomicUsize>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<M> Job<M> {
+    /// Queues `message` for the next `Worker::drain`. A no-op once the app is gone.
+    pub fn send(&self, message: M) {
+        let _ = self.tx.send(message);
+    }
+
+    /// True after `Worker::shutdown`: finish the item in hand, start no more.
+    pub fn stopping(&self) -> bool {
+        self.stopping.load(Ordering::Relaxed)
+    }
+}
+
+impl<M: Send + 'static> Worker<M> {
+    /// Sized like rayon's global pool, since scans fan out over the pool
+    /// they run on. Compression and restores bring their own, capped by
+    /// `max_jobs`, and only coordinate from here.
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        let pool = ThreadPoolBuilder::new()
+            .thread_name(|i| format!("piper-worker-{}", i))
+            .build()
+            .ok();
+        Worker {
+            tx,
+            rx,
+            pool,
+            running: Arc::new(AtomicUsize::new(0)),
+            stopping: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// A handle into the result stream, as each job gets one.
+    pub fn job(&self) -> Job<M> {
+        Job { tx: self.tx.clone(), stopping: self.stopping.clone() }
+    }
+
+    /// Runs `work` on the pool; `shutdown` waits for it. For jobs that
+    /// change files, so quitting can't cut one off halfway.
+    pub fn spawn(&self, work: impl FnOnce(Job<M>) + Send + 'static) {
+        self.running.fetch_add(1, Ordering::Relaxed);
+        let running = Running(self.running.clone());
+        self.spawn_detached(move |job| {
+            let _running = running;
+            work(job);
+        });
+    }
+
+    /// Like `spawn`, for read-only jobs (scans, measuring) that can simply
+    /// be dropped at exit.
+    pub fn spawn_detached(&self, work: impl FnOnce(Job<M>) + Send + 'static) {
+        let job = self.job();
+        match &self.pool {
+            Some(pool) => pool.spawn(move || work(job)),
+            None => {
+                thread::spawn(move || work(job));
+            }
+        }
+    }
+
+    /// Everything reported since the last call, oldest first.
+    pub fn drain(&self) -> Vec<M> {
+        self.rx.try_iter().collect()
+    }
+
+    /// Whether a `spawn`ed job is still running.
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub fn is_busy(&self) -> bool {
+        self.running.load(Ordering::Relaxed) > 0
+    }
+
+    /// Tells jobs to stop starting items, then waits up to `timeout` for
+    /// the `spawn`ed ones. False if some are still running.
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        self.stopping.store(true, Ordering::Relaxed);
+        let deadline = Instant::now() + timeout;
+        while self.is_busy() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        !self.is_busy()
+    }
+}
+
+impl<M: Send + 'static> Default for Worker<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_jobs_share_one_stream() {
+        let worker = Worker::new();
+        worker.spawn_detached(|job| job.send("scan"));
+        worker.spawn(|job| {
+            thread::sleep(Duration::from_millis(50));
+            job.send("restore");
+        });
+
+        assert!(worker.shutdown(Duration::from_secs(5)));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut messages = Vec::new();
+        while messages.len() < 2 && Instant::now() < deadline {
+            // The detached one isn't waited for
+            messages.extend(worker.drain());
+            thread::sleep(Duration::from_millis(10));
+        }
+        messages.sort();
+        assert_eq!(messages, vec!["restore", "scan"]);
+        assert!(worker.job().stopping());
+    }
+}